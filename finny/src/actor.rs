@@ -0,0 +1,193 @@
+//! Runs the FSM on a dedicated thread. The events are sent into the thread's mailbox, which is
+//! processed together with the due timers, sleeping until the next timer in between.
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::{
+    report_errors, FsmBackend, FsmError, FsmEventQueue, FsmFrontend, FsmOutputs, FsmOutputsNull,
+    FsmResult, FsmTimers, Inspect,
+};
+
+/// A closure that is executed on the actor's thread.
+type FsmQuery<F, Q, I, T, O, QI> = Box<dyn FnOnce(&mut FsmFrontend<F, Q, I, T, O, QI>) + Send>;
+
+enum Message<F, Q, I, T, O, QI>
+where
+    F: FsmBackend,
+    Q: FsmEventQueue<F>,
+    I: Inspect,
+    T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
+{
+    Event(<F as FsmBackend>::Events),
+    Query(FsmQuery<F, Q, I, T, O, QI>),
+    Shutdown,
+}
+
+/// The handle of an FSM that runs on its own thread. The thread is shut down once the handle
+/// is dropped.
+pub struct FsmActor<F, Q, I, T, O = FsmOutputsNull, QI = Q>
+where
+    F: FsmBackend,
+    Q: FsmEventQueue<F>,
+    I: Inspect,
+    T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
+{
+    mailbox: Sender<Message<F, Q, I, T, O, QI>>,
+    thread: Option<JoinHandle<FsmResult<()>>>,
+}
+
+impl<F, Q, I, T, O, QI> FsmActor<F, Q, I, T, O, QI>
+where
+    F: FsmBackend + 'static,
+    Q: FsmEventQueue<F> + 'static,
+    I: Inspect + 'static,
+    T: FsmTimers<F> + 'static,
+    O: FsmOutputs<F> + 'static,
+    QI: FsmEventQueue<F> + 'static,
+    <F as FsmBackend>::Events: Send,
+{
+    /// Build the frontend on a new thread and start it. The frontend itself doesn't have to be
+    /// `Send`, only the events are moved between the threads. The errors of the dispatched
+    /// events are left to the inspection.
+    pub fn spawn<B>(build: B) -> FsmResult<Self>
+    where
+        B: FnOnce() -> FsmResult<FsmFrontend<F, Q, I, T, O, QI>> + Send + 'static,
+    {
+        Self::spawn_with_errors(build, |_| ())
+    }
+
+    /// Like `spawn`, and the errors of the dispatched events and of the timers are passed to
+    /// the callback, on the actor's thread.
+    pub fn spawn_with_errors<B, E>(build: B, on_error: E) -> FsmResult<Self>
+    where
+        B: FnOnce() -> FsmResult<FsmFrontend<F, Q, I, T, O, QI>> + Send + 'static,
+        E: FnMut(FsmError) + Send + 'static,
+    {
+        let (mailbox, receiver) = mpsc::channel();
+        let (started, started_receiver) = mpsc::sync_channel(1);
+
+        let thread = thread::spawn(move || {
+            let fsm = build().and_then(|mut fsm| fsm.start().map(|_| fsm));
+            match fsm {
+                Ok(fsm) => {
+                    let _ = started.send(Ok(()));
+                    Self::run(fsm, receiver, on_error)
+                }
+                Err(e) => {
+                    let _ = started.send(Err(e));
+                    Ok(())
+                }
+            }
+        });
+
+        started_receiver
+            .recv()
+            .map_err(|_| FsmError::ActorStopped)??;
+
+        Ok(FsmActor {
+            mailbox,
+            thread: Some(thread),
+        })
+    }
+
+    /// Send the event into the actor's mailbox. Fails with `FsmError::ActorStopped` if the
+    /// actor's thread has stopped.
+    pub fn send<E>(&self, event: E) -> FsmResult<()>
+    where
+        E: Into<<F as FsmBackend>::Events>,
+    {
+        self.post(Message::Event(event.into()))
+    }
+
+    /// Run the closure on the actor's thread, in between the events, and return its result.
+    pub fn query<R, C>(&self, query: C) -> FsmResult<R>
+    where
+        C: FnOnce(&mut FsmFrontend<F, Q, I, T, O, QI>) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (reply, reply_receiver) = mpsc::sync_channel(1);
+        self.post(Message::Query(Box::new(move |fsm| {
+            let _ = reply.send(query(fsm));
+        })))?;
+
+        reply_receiver.recv().map_err(|_| FsmError::ActorStopped)
+    }
+
+    /// Stop the FSM and wait for the actor's thread to finish. The events that are already
+    /// in the mailbox are dispatched first.
+    pub fn shutdown(mut self) -> FsmResult<()> {
+        self.join()
+    }
+
+    fn post(&self, message: Message<F, Q, I, T, O, QI>) -> FsmResult<()> {
+        self.mailbox
+            .send(message)
+            .map_err(|_| FsmError::ActorStopped)
+    }
+
+    fn run<E>(
+        mut fsm: FsmFrontend<F, Q, I, T, O, QI>,
+        mailbox: Receiver<Message<F, Q, I, T, O, QI>>,
+        mut on_error: E,
+    ) -> FsmResult<()>
+    where
+        E: FnMut(FsmError),
+    {
+        loop {
+            let message = match fsm.timers.time_to_next_timer() {
+                Some(timeout) => mailbox.recv_timeout(timeout),
+                None => mailbox.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match message {
+                Ok(Message::Event(event)) => report_errors(fsm.dispatch(event), &mut on_error),
+                Ok(Message::Query(query)) => query(&mut fsm),
+                Ok(Message::Shutdown) | Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => (),
+            }
+
+            report_errors(fsm.dispatch_timer_events(), &mut on_error);
+        }
+
+        fsm.stop()
+    }
+}
+
+impl<F, Q, I, T, O, QI> FsmActor<F, Q, I, T, O, QI>
+where
+    F: FsmBackend,
+    Q: FsmEventQueue<F>,
+    I: Inspect,
+    T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
+{
+    fn join(&mut self) -> FsmResult<()> {
+        match self.thread.take() {
+            Some(thread) => {
+                let _ = self.mailbox.send(Message::Shutdown);
+                thread.join().unwrap_or(Err(FsmError::ActorStopped))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<F, Q, I, T, O, QI> Drop for FsmActor<F, Q, I, T, O, QI>
+where
+    F: FsmBackend,
+    Q: FsmEventQueue<F>,
+    I: Inspect,
+    T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
+{
+    fn drop(&mut self) {
+        let _ = self.join();
+    }
+}
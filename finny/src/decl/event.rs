@@ -36,6 +36,29 @@ impl<'a, TFsm, TContext, TEvent, TState> FsmEventBuilderState<'a, TFsm, TContext
             _state_to: PhantomData,
         }
     }
+
+    /// Transition into this state, which is constructed from the event and the context on every
+    /// transition. The new instance replaces the stored one before the transition's action.
+    ///
+    /// The default states storage holds every state from the start, so the target state still
+    /// has to implement `FsmStateFactory`, through `Default` or by hand. With the enum storage
+    /// of `states_storage_enum` the state only exists while it's active and the constructor is
+    /// all it needs.
+    ///
+    /// Example : `.transition_to_with::<Connected>(|ev, ctx| Connected { session: ev.session.clone() })`
+    pub fn transition_to_with<'b, TStateTo, TConstructor>(
+        &'b self,
+        _constructor: TConstructor,
+    ) -> FsmEventBuilderTransitionFull<'b, TFsm, TContext, TEvent, TState, TStateTo>
+    where
+        TFsm: FsmBackend,
+        TConstructor: Fn(&TEvent, &EventContext<'a, TFsm, FsmQueueMock<TFsm>>) -> TStateTo,
+    {
+        FsmEventBuilderTransitionFull {
+            _transition_from: self,
+            _state_to: PhantomData,
+        }
+    }
 }
 
 pub struct FsmEventBuilderTransition<'a, TFsm, TContext, TEvent, TState> {
@@ -48,15 +71,28 @@ where
     TFsm: FsmBackend,
 {
     /// An action that happens when the currently active state receives this event. No transitions.
-    pub fn action<
-        TAction: Fn(&TEvent, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>, &mut TState),
-    >(
+    /// An optional fourth argument borrows the other states of the machine, see [`FsmActionFn`].
+    pub fn action<TArgs, TAction: FsmActionFn<'a, TFsm, TEvent, TState, TArgs>>(
         &mut self,
         _action: TAction,
     ) -> &mut Self {
         self
     }
 
+    /// An async action that happens when the currently active state receives this event. The
+    /// closure returns a future that can't borrow the context, which is awaited by
+    /// `dispatch_async` once the step is done. The event that the future completes with is
+    /// dispatched before any other event. The sync dispatch fails with `FsmError::NotSupported`
+    /// if it runs the action. An `async` closure is its own future.
+    #[cfg(feature = "alloc")]
+    pub fn action_async<TArgs, TAction, TTask>(&mut self, _action: TAction) -> &mut Self
+    where
+        TAction: FsmActionFn<'a, TFsm, TEvent, TState, TArgs, TTask>,
+        TTask: Future<Output = Option<<TFsm as FsmBackend>::Events>> + Send + 'static,
+    {
+        self
+    }
+
     /// A guard for executing this action.
     pub fn guard<
         TGuard: Fn(
@@ -87,14 +123,11 @@ impl<'a, TFsm, TContext, TEvent, TStateFrom, TStateTo>
 where
     TFsm: FsmBackend,
 {
-    /// An action that happens between the transitions from the two states.
+    /// An action that happens between the transitions from the two states. An optional fifth
+    /// argument borrows the other states of the machine, see [`FsmActionFn`].
     pub fn action<
-        TAction: Fn(
-            &TEvent,
-            &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>,
-            &mut TStateFrom,
-            &mut TStateTo,
-        ),
+        TArgs,
+        TAction: FsmTransitionActionFn<'a, TFsm, TEvent, TStateFrom, TStateTo, TArgs>,
     >(
         &mut self,
         _action: TAction,
@@ -102,6 +135,17 @@ where
         self
     }
 
+    /// An async action that happens between the transitions from the two states, see
+    /// [`FsmEventBuilderTransition::action_async`].
+    #[cfg(feature = "alloc")]
+    pub fn action_async<TArgs, TAction, TTask>(&mut self, _action: TAction) -> &mut Self
+    where
+        TAction: FsmTransitionActionFn<'a, TFsm, TEvent, TStateFrom, TStateTo, TArgs, TTask>,
+        TTask: Future<Output = Option<<TFsm as FsmBackend>::Events>> + Send + 'static,
+    {
+        self
+    }
+
     /// A guard for starting this transition from one state to another, including executing the action.
     pub fn guard<
         TGuard: Fn(
@@ -121,3 +165,59 @@ where
         self
     }
 }
+
+/// The action of an internal or a self transition. The optional last argument is a struct generated
+/// for this transition, named `{Transition}Siblings`, that mutably borrows all the other states of
+/// the machine. They are accessible through its `AsRef` and `AsMut` implementations.
+pub trait FsmActionFn<'a, TFsm, TEvent, TState, TArgs, TOutput = ()> {}
+
+impl<'a, TFsm, TEvent, TState, TOutput, TAction> FsmActionFn<'a, TFsm, TEvent, TState, (), TOutput>
+    for TAction
+where
+    TFsm: FsmBackend,
+    TAction: Fn(&TEvent, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>, &mut TState) -> TOutput,
+{
+}
+
+impl<'a, TFsm, TEvent, TState, TSiblings, TOutput, TAction>
+    FsmActionFn<'a, TFsm, TEvent, TState, (TSiblings,), TOutput> for TAction
+where
+    TFsm: FsmBackend,
+    TAction: Fn(
+        &TEvent,
+        &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>,
+        &mut TState,
+        &mut TSiblings,
+    ) -> TOutput,
+{
+}
+
+/// The action of a transition between two states, with the optional sibling states.
+pub trait FsmTransitionActionFn<'a, TFsm, TEvent, TStateFrom, TStateTo, TArgs, TOutput = ()> {}
+
+impl<'a, TFsm, TEvent, TStateFrom, TStateTo, TOutput, TAction>
+    FsmTransitionActionFn<'a, TFsm, TEvent, TStateFrom, TStateTo, (), TOutput> for TAction
+where
+    TFsm: FsmBackend,
+    TAction: Fn(
+        &TEvent,
+        &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>,
+        &mut TStateFrom,
+        &mut TStateTo,
+    ) -> TOutput,
+{
+}
+
+impl<'a, TFsm, TEvent, TStateFrom, TStateTo, TSiblings, TOutput, TAction>
+    FsmTransitionActionFn<'a, TFsm, TEvent, TStateFrom, TStateTo, (TSiblings,), TOutput> for TAction
+where
+    TFsm: FsmBackend,
+    TAction: Fn(
+        &TEvent,
+        &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>,
+        &mut TStateFrom,
+        &mut TStateTo,
+        &mut TSiblings,
+    ) -> TOutput,
+{
+}
@@ -26,9 +26,42 @@ where
     /// Example : `fsm.initial_states<(StateA, StateX)>()`
     pub fn initial_states<TStates>(&mut self) {}
 
+    /// Picks the initial state of the state machine when it is started, based on the context. The
+    /// conditions are evaluated in order on every `start()`, and for submachines on every re-entry.
+    /// Replaces `initial_state`.
+    ///
+    /// Example : `fsm.initial_choice().when(|ctx| ctx.resume, ResumeState).otherwise::<FreshState>()`
+    pub fn initial_choice(&mut self) -> FsmInitialChoiceBuilder<TFsm, TContext> {
+        FsmInitialChoiceBuilder {
+            _fsm: PhantomData,
+            _context: PhantomData,
+        }
+    }
+
     /// Require the `Debug` trait on the Events.
     pub fn events_debug(&mut self) {}
 
+    /// Sets the type of the outputs that the actions emit with `ctx.emit(output)`. The outputs are
+    /// collected by the frontend's output sink. Defaults to `()`.
+    ///
+    /// Example : `fsm.outputs::<Command>()`
+    pub fn outputs<TOutputs>(&mut self) {}
+
+    /// Lets the actions delay events with `ctx.enqueue_after(delay, event)`, up to `N` of them at
+    /// the same time. Each delayed event takes a timer of the frontend. The events delayed by the
+    /// submachines take the slots of the top machine.
+    ///
+    /// Example : `fsm.delayed_events::<8>()`
+    pub fn delayed_events<const N: usize>(&mut self) {}
+
+    /// Store only the active state of each region, in a generated enum, instead of keeping all
+    /// of the states alive. States are constructed on entry, before the previous state's exit
+    /// action, and dropped after the transition's action. Accessing an inactive state panics.
+    /// The sizes are reported by the `SIZE` and `REGION_SIZES` constants on the states struct.
+    ///
+    /// Submachines and sibling states in actions aren't supported.
+    pub fn states_storage_enum(&mut self) {}
+
     /// Adds some information about a state.
     pub fn state<TState>(&mut self) -> FsmStateBuilder<TFsm, TContext, TState> {
         FsmStateBuilder {
@@ -60,3 +93,25 @@ where
         BuiltFsm
     }
 }
+
+/// Builds the conditional initial state of the machine.
+pub struct FsmInitialChoiceBuilder<TFsm, TContext> {
+    _fsm: PhantomData<TFsm>,
+    _context: PhantomData<TContext>,
+}
+
+impl<TFsm, TContext> FsmInitialChoiceBuilder<TFsm, TContext>
+where
+    TFsm: FsmBackend<Context = TContext>,
+{
+    /// Start in this state if the condition holds and none of the previous ones did.
+    pub fn when<TCondition, TState>(&mut self, _condition: TCondition, _state: TState) -> &mut Self
+    where
+        TCondition: Fn(&TContext) -> bool,
+    {
+        self
+    }
+
+    /// The initial state if none of the conditions hold. Required!
+    pub fn otherwise<TState>(&mut self) {}
+}
@@ -1,7 +1,7 @@
 use crate::{lib::*, TimerFsmSettings};
 
 use super::{event::FsmEventBuilderState, FsmQueueMock};
-use crate::{EventContext, FsmBackend};
+use crate::{EventContext, FsmBackend, FsmEvent};
 
 pub struct FsmStateBuilder<TFsm, TContext, TState> {
     pub(crate) _state: PhantomData<TState>,
@@ -13,22 +13,34 @@ impl<TFsm, TContext, TState> FsmStateBuilder<TFsm, TContext, TState>
 where
     TFsm: FsmBackend,
 {
-    /// Execute this action when entering the state.
-    pub fn on_entry<
-        'a,
-        TAction: Fn(&mut TState, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>),
-    >(
+    /// Execute this action when entering the state. The event that triggered the transition
+    /// can be accessed with an optional third argument.
+    pub fn on_entry<'a, TArgs, TAction: FsmStateActionFn<'a, TFsm, TState, TArgs>>(
         &self,
         _action: TAction,
     ) -> &Self {
         self
     }
 
-    /// Execute this action when exiting the state.
-    pub fn on_exit<
-        'a,
-        TAction: Fn(&mut TState, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>),
-    >(
+    /// Execute this async action when entering the state. The closure returns a future that
+    /// can't borrow the context, which is awaited by `dispatch_async` once the step is done.
+    /// The event that the future completes with is dispatched before any other event. The sync
+    /// dispatch fails with `FsmError::NotSupported` if it enters the state. An `async` closure
+    /// is its own future.
+    ///
+    /// Example : `.on_entry_async(|_state, ctx| { let db = ctx.db.clone(); async move { db.load().await; Some(Loaded.into()) } })`
+    #[cfg(feature = "alloc")]
+    pub fn on_entry_async<'a, TArgs, TAction, TTask>(&self, _action: TAction) -> &Self
+    where
+        TAction: FsmStateActionFn<'a, TFsm, TState, TArgs, TTask>,
+        TTask: Future<Output = Option<<TFsm as FsmBackend>::Events>> + Send + 'static,
+    {
+        self
+    }
+
+    /// Execute this action when exiting the state. The event that triggered the transition
+    /// can be accessed with an optional third argument.
+    pub fn on_exit<'a, TArgs, TAction: FsmStateActionFn<'a, TFsm, TState, TArgs>>(
         &self,
         _action: TAction,
     ) -> &Self {
@@ -43,6 +55,21 @@ where
         }
     }
 
+    /// Transition out of this state once the condition is met. The condition is evaluated once
+    /// at the end of every run-to-completion step of the machine and after the start, no event
+    /// is required. A condition that stays true fires again only after the next step.
+    pub fn when<
+        'a,
+        TCondition: Fn(&EventContext<'a, TFsm, FsmQueueMock<TFsm>>, &<TFsm as FsmBackend>::States) -> bool,
+    >(
+        &self,
+        _condition: TCondition,
+    ) -> FsmChangeBuilderState<'_, TFsm, TContext, TState> {
+        FsmChangeBuilderState {
+            _state_builder: self,
+        }
+    }
+
     /// Start a new timer when entering this state. The timer should be unit struct with a implemented
     /// Default trait. The timer is setup within a closure and the trigger is another closure
     /// that returns an event to be enqueued in the FSM.
@@ -59,6 +86,28 @@ where
     }
 }
 
+/// The entry or exit action of a state, with or without the triggering event.
+pub trait FsmStateActionFn<'a, TFsm, TState, TArgs, TOutput = ()> {}
+
+impl<'a, TFsm, TState, TOutput, TAction> FsmStateActionFn<'a, TFsm, TState, (), TOutput> for TAction
+where
+    TFsm: FsmBackend,
+    TAction: Fn(&mut TState, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>) -> TOutput,
+{
+}
+
+impl<'a, TFsm, TState, TOutput, TAction>
+    FsmStateActionFn<'a, TFsm, TState, (FsmEvent<TFsm::Events, TFsm::Timers>,), TOutput> for TAction
+where
+    TFsm: FsmBackend,
+    TAction: Fn(
+        &mut TState,
+        &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>,
+        &FsmEvent<TFsm::Events, TFsm::Timers>,
+    ) -> TOutput,
+{
+}
+
 pub struct FsmStateTimerBuilder<'a, TFsm, TContext, TState> {
     _state: &'a FsmStateBuilder<TFsm, TContext, TState>,
 }
@@ -70,3 +119,68 @@ where
     /// Assign this type to the timer. The struct for it will be auto-generated.
     pub fn with_timer_ty<TTimer>(self) {}
 }
+
+pub struct FsmChangeBuilderState<'a, TFsm, TContext, TState> {
+    _state_builder: &'a FsmStateBuilder<TFsm, TContext, TState>,
+}
+
+impl<'a, TFsm, TContext, TState> FsmChangeBuilderState<'a, TFsm, TContext, TState> {
+    /// Transition into this state when the condition is met.
+    pub fn transition_to<'b, TStateTo>(
+        &'b self,
+    ) -> FsmChangeBuilderTransition<'b, TFsm, TContext, TState, TStateTo> {
+        FsmChangeBuilderTransition {
+            _transition_from: self,
+            _state_to: PhantomData,
+        }
+    }
+}
+
+pub struct FsmChangeBuilderTransition<'a, TFsm, TContext, TStateFrom, TStateTo> {
+    _transition_from: &'a FsmChangeBuilderState<'a, TFsm, TContext, TStateFrom>,
+    _state_to: PhantomData<TStateTo>,
+}
+
+impl<'a, TFsm, TContext, TStateFrom, TStateTo>
+    FsmChangeBuilderTransition<'a, TFsm, TContext, TStateFrom, TStateTo>
+where
+    TFsm: FsmBackend,
+{
+    /// An action that happens between the transitions from the two states. An optional fourth
+    /// argument borrows the other states of the machine, see [`super::FsmActionFn`].
+    pub fn action<TArgs, TAction: FsmChangeActionFn<'a, TFsm, TStateFrom, TStateTo, TArgs>>(
+        &mut self,
+        _action: TAction,
+    ) -> &mut Self {
+        self
+    }
+
+    /// A type for this transition. The struct for the transition will be generated.
+    pub fn with_transition_ty<TTransition>(&mut self) -> &mut Self {
+        self
+    }
+}
+
+/// The action of a change transition, with the optional sibling states.
+pub trait FsmChangeActionFn<'a, TFsm, TStateFrom, TStateTo, TArgs> {}
+
+impl<'a, TFsm, TStateFrom, TStateTo, TAction> FsmChangeActionFn<'a, TFsm, TStateFrom, TStateTo, ()>
+    for TAction
+where
+    TFsm: FsmBackend,
+    TAction: Fn(&mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>, &mut TStateFrom, &mut TStateTo),
+{
+}
+
+impl<'a, TFsm, TStateFrom, TStateTo, TSiblings, TAction>
+    FsmChangeActionFn<'a, TFsm, TStateFrom, TStateTo, (TSiblings,)> for TAction
+where
+    TFsm: FsmBackend,
+    TAction: Fn(
+        &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>,
+        &mut TStateFrom,
+        &mut TStateTo,
+        &mut TSiblings,
+    ),
+{
+}
@@ -1,6 +1,8 @@
 use crate::{lib::*, EventContext, FsmBackend};
 
-use super::{FsmEventBuilderState, FsmQueueMock, FsmStateBuilder};
+use super::{
+    FsmChangeBuilderState, FsmEventBuilderState, FsmQueueMock, FsmStateActionFn, FsmStateBuilder,
+};
 
 pub struct FsmSubMachineBuilder<TFsm, TContext, TSubMachine> {
     pub(crate) _fsm: PhantomData<TFsm>,
@@ -23,22 +25,29 @@ where
         self
     }
 
-    /// Execute this action when entering the sub-machine state.
-    pub fn on_entry<
-        'a,
-        TAction: Fn(&mut TSubMachine, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>),
-    >(
+    /// Execute this action when entering the sub-machine state. The event that triggered the
+    /// transition can be accessed with an optional third argument.
+    pub fn on_entry<'a, TArgs, TAction: FsmStateActionFn<'a, TFsm, TSubMachine, TArgs>>(
         &self,
         _action: TAction,
     ) -> &Self {
         self
     }
 
-    /// Execute this action when exiting the sub-machine state.
-    pub fn on_exit<
-        'a,
-        TAction: Fn(&mut TSubMachine, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>),
-    >(
+    /// Execute this async action when entering the sub-machine state, see
+    /// [`FsmStateBuilder::on_entry_async`].
+    #[cfg(feature = "alloc")]
+    pub fn on_entry_async<'a, TArgs, TAction, TTask>(&self, _action: TAction) -> &Self
+    where
+        TAction: FsmStateActionFn<'a, TFsm, TSubMachine, TArgs, TTask>,
+        TTask: Future<Output = Option<<TFsm as FsmBackend>::Events>> + Send + 'static,
+    {
+        self
+    }
+
+    /// Execute this action when exiting the sub-machine state. The event that triggered the
+    /// transition can be accessed with an optional third argument.
+    pub fn on_exit<'a, TArgs, TAction: FsmStateActionFn<'a, TFsm, TSubMachine, TArgs>>(
         &self,
         _action: TAction,
     ) -> &Self {
@@ -52,4 +61,16 @@ where
             _event: PhantomData,
         }
     }
+
+    /// Transition out of this submachine's state once the condition is met. Evaluated after the
+    /// submachine's own change transitions.
+    pub fn when<
+        'a,
+        TCondition: Fn(&EventContext<'a, TFsm, FsmQueueMock<TFsm>>, &<TFsm as FsmBackend>::States) -> bool,
+    >(
+        &self,
+        _condition: TCondition,
+    ) -> FsmChangeBuilderState<'_, TFsm, TContext, TSubMachine> {
+        self._state_builder.when(_condition)
+    }
 }
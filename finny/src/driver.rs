@@ -0,0 +1,60 @@
+//! An async driver for the FSM on top of the Tokio runtime. The events are awaited on a channel
+//! and the timers are awaited on `TimersTokio`, each one is dispatched as soon as it's ready.
+
+use tokio::sync::mpsc::Receiver;
+
+use crate::timers::tokio::TimersTokio;
+use crate::{
+    report_errors, FsmBackend, FsmError, FsmEventQueue, FsmFrontend, FsmOutputs, FsmTimers, Inspect,
+};
+
+enum Ready<E> {
+    Event(Option<E>),
+    Timer,
+}
+
+/// Dispatch the events from the channel and the triggered timers until all of the channel's
+/// senders are dropped. The machine has to be started first. The errors of the dispatched
+/// events are left to the inspection.
+pub async fn run_tokio<F, Q, I, O, QI>(
+    fsm: &mut FsmFrontend<F, Q, I, TimersTokio<F>, O, QI>,
+    events: &mut Receiver<<F as FsmBackend>::Events>,
+) where
+    F: FsmBackend,
+    Q: FsmEventQueue<F>,
+    I: Inspect,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
+    TimersTokio<F>: FsmTimers<F>,
+{
+    run_tokio_with_errors(fsm, events, |_| ()).await
+}
+
+/// Like `run_tokio`, and the errors of the dispatched events and of the timers are passed to
+/// the callback.
+pub async fn run_tokio_with_errors<F, Q, I, O, QI, E>(
+    fsm: &mut FsmFrontend<F, Q, I, TimersTokio<F>, O, QI>,
+    events: &mut Receiver<<F as FsmBackend>::Events>,
+    mut on_error: E,
+) where
+    F: FsmBackend,
+    Q: FsmEventQueue<F>,
+    I: Inspect,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
+    TimersTokio<F>: FsmTimers<F>,
+    E: FnMut(FsmError),
+{
+    loop {
+        let ready = tokio::select! {
+            event = events.recv() => Ready::Event(event),
+            _ = fsm.timers.wait() => Ready::Timer,
+        };
+
+        match ready {
+            Ready::Event(Some(event)) => report_errors(fsm.dispatch(event), &mut on_error),
+            Ready::Event(None) => break,
+            Ready::Timer => report_errors(fsm.dispatch_timer_events(), &mut on_error),
+        }
+    }
+}
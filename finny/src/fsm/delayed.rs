@@ -0,0 +1,240 @@
+use crate::lib::*;
+use crate::{FsmBackend, FsmEnqueueResult, FsmError, FsmResult, FsmTimers, Inspect, TimerSettings};
+
+/// The timer slot of a delayed event. The timers of the machines that were built with
+/// `fsm.delayed_events::<N>()` have a variant for these slots.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FsmDelayedSlot(usize);
+
+impl FsmDelayedSlot {
+    pub fn new(index: usize) -> Self {
+        FsmDelayedSlot(index)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// Schedules the events that the actions delay with `ctx.enqueue_after(delay, event)`.
+pub trait FsmDelayedSender<F: FsmBackend> {
+    /// Enqueue the event after the delay. The event is returned if all of the slots are taken.
+    fn enqueue_after(
+        &mut self,
+        delay: Duration,
+        event: <F as FsmBackend>::Events,
+    ) -> FsmEnqueueResult<F>;
+}
+
+/// A delayed event in its slot.
+pub struct FsmDelayedEvent<F: FsmBackend> {
+    event: <F as FsmBackend>::Events,
+    /// Set until the timer of the slot is created.
+    pending: Option<Duration>,
+}
+
+/// The slots of the delayed events, an array of `fsm.delayed_events::<N>()` slots.
+pub trait FsmDelayedSlots<F: FsmBackend> {
+    fn new() -> Self;
+    fn slots(&self) -> &[Option<FsmDelayedEvent<F>>];
+    fn slots_mut(&mut self) -> &mut [Option<FsmDelayedEvent<F>>];
+}
+
+impl<F: FsmBackend, const N: usize> FsmDelayedSlots<F> for [Option<FsmDelayedEvent<F>>; N] {
+    fn new() -> Self {
+        core::array::from_fn(|_| None)
+    }
+
+    fn slots(&self) -> &[Option<FsmDelayedEvent<F>>] {
+        self
+    }
+
+    fn slots_mut(&mut self) -> &mut [Option<FsmDelayedEvent<F>>] {
+        self
+    }
+}
+
+/// The delayed events of the frontend. Each one takes a slot and a timer of the frontend's
+/// `FsmTimers`, and is put into the queue once its timer triggers.
+pub struct FsmDelayedEvents<F: FsmBackend> {
+    slots: <F as FsmBackend>::DelayedSlots,
+    /// The events that were dropped since the last dispatch outcome.
+    dropped: usize,
+}
+
+impl<F: FsmBackend> FsmDelayedEvents<F> {
+    pub fn new() -> Self {
+        FsmDelayedEvents {
+            slots: FsmDelayedSlots::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Number of the events that are waiting for their timers.
+    pub fn len(&self) -> usize {
+        self.slots.slots().iter().filter(|s| s.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Create the timers of the newly delayed events. The event is dropped if its timer
+    /// couldn't be created, and counted in the next dispatch outcome.
+    pub(crate) fn start_timers<T: FsmTimers<F>, I: Inspect>(
+        &mut self,
+        timers: &mut T,
+        inspect: &mut I,
+    ) {
+        for (index, slot) in self.slots.slots_mut().iter_mut().enumerate() {
+            let delay = match slot.as_mut().and_then(|s| s.pending.take()) {
+                Some(delay) => delay,
+                None => continue,
+            };
+
+            let settings = TimerSettings {
+                enabled: true,
+                timeout: delay,
+                renew: false,
+            };
+            if let Some(id) = F::delayed_timer(FsmDelayedSlot::new(index)) {
+                if let Err(ref e) = timers.create(id.clone(), &settings) {
+                    inspect.for_timer::<F>(id).on_error(
+                        "Failed to create the timer, the delayed event was dropped",
+                        e,
+                    );
+                    *slot = None;
+                    self.dropped += 1;
+                }
+            }
+        }
+    }
+
+    /// Cancel the timers of the delayed events and drop the events.
+    pub(crate) fn cancel_timers<T: FsmTimers<F>>(&mut self, timers: &mut T) -> FsmResult<()> {
+        let mut result = Ok(());
+        for (index, slot) in self.slots.slots_mut().iter_mut().enumerate() {
+            if slot.take().is_none() {
+                continue;
+            }
+
+            if let Some(id) = F::delayed_timer(FsmDelayedSlot::new(index)) {
+                if let Err(e) = timers.cancel(id) {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Take the event of the triggered slot.
+    pub(crate) fn take(&mut self, slot: FsmDelayedSlot) -> Option<<F as FsmBackend>::Events> {
+        self.slots
+            .slots_mut()
+            .get_mut(slot.index())
+            .and_then(|s| s.take())
+            .map(|s| s.event)
+    }
+
+    /// Take the number of the events that were dropped since the last call.
+    pub(crate) fn take_dropped(&mut self) -> usize {
+        core::mem::take(&mut self.dropped)
+    }
+}
+
+impl<F: FsmBackend> Default for FsmDelayedEvents<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: FsmBackend> FsmDelayedSender<F> for FsmDelayedEvents<F> {
+    fn enqueue_after(
+        &mut self,
+        delay: Duration,
+        event: <F as FsmBackend>::Events,
+    ) -> FsmEnqueueResult<F> {
+        let slots = self.slots.slots_mut();
+        if slots.is_empty() {
+            return Err((FsmError::NotSupported, event));
+        }
+
+        match slots.iter_mut().find(|s| s.is_none()) {
+            Some(slot) => {
+                *slot = Some(FsmDelayedEvent {
+                    event,
+                    pending: Some(delay),
+                });
+                Ok(())
+            }
+            None => Err((FsmError::QueueOverCapacity, event)),
+        }
+    }
+}
+
+/// Used to funnel the delayed events of the sub-machine up to the parent's slots.
+pub struct FsmDelayedSenderSub<'a, F, FSub>
+where
+    F: FsmBackend,
+{
+    pub parent: &'a mut dyn FsmDelayedSender<F>,
+    pub _sub_fsm: PhantomData<FSub>,
+}
+
+impl<'a, F, FSub> FsmDelayedSender<FSub> for FsmDelayedSenderSub<'a, F, FSub>
+where
+    F: FsmBackend,
+    FSub: FsmBackend,
+    <F as FsmBackend>::Events: From<<FSub as FsmBackend>::Events>,
+    <FSub as FsmBackend>::Events: TryFrom<<F as FsmBackend>::Events>,
+{
+    fn enqueue_after(
+        &mut self,
+        delay: Duration,
+        event: <FSub as FsmBackend>::Events,
+    ) -> FsmEnqueueResult<FSub> {
+        match self.parent.enqueue_after(delay, event.into()) {
+            Ok(()) => Ok(()),
+            Err((e, event)) => match event.try_into() {
+                Ok(event) => Err((e, event)),
+                Err(_) => {
+                    unreachable!("The parent machine's event always wraps the sub-machine's.")
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+use super::tests_fsm::TestFsm;
+
+#[test]
+fn test_delayed_slots() {
+    use super::tests_fsm::{EventA, Events, FsmBackendTimers, TEST_FSM_DELAYED_SLOTS};
+    use crate::{inspect::null::InspectNull, FsmTimersNull};
+
+    let mut delayed = FsmDelayedEvents::<TestFsm>::new();
+    for n in 0..TEST_FSM_DELAYED_SLOTS {
+        assert!(delayed
+            .enqueue_after(Duration::from_secs(1), EventA { n }.into())
+            .is_ok());
+    }
+    match delayed.enqueue_after(Duration::from_secs(1), EventA { n: 100 }.into()) {
+        Err((FsmError::QueueOverCapacity, Events::EventA(EventA { n: 100 }))) => (),
+        _ => panic!("Expected the slots to be taken."),
+    }
+
+    let slot = FsmDelayedSlot::new(2);
+    assert_eq!(
+        Some(FsmBackendTimers::FsmDelayed(slot)),
+        TestFsm::delayed_timer(slot)
+    );
+    assert_eq!(Some(Events::EventA(EventA { n: 2 })), delayed.take(slot));
+    assert_eq!(TEST_FSM_DELAYED_SLOTS - 1, delayed.len());
+
+    // the events whose timers can't be created are dropped
+    delayed.start_timers(&mut FsmTimersNull, &mut InspectNull::new());
+    assert!(delayed.is_empty());
+    assert_eq!(TEST_FSM_DELAYED_SLOTS - 1, delayed.take_dropped());
+    assert_eq!(0, delayed.take_dropped());
+}
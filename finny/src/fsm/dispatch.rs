@@ -1,7 +1,9 @@
 use crate::{lib::*, FsmTimers, FsmTimersSub};
 use crate::{
-    EventContext, FsmBackend, FsmBackendImpl, FsmEvent, FsmEventQueue, FsmEventQueueSub,
-    FsmRegionId, FsmResult, Inspect,
+    EventContext, FsmAsyncTasks, FsmAsyncTasksSub, FsmBackend, FsmBackendImpl, FsmDelayedSender,
+    FsmDelayedSenderSub, FsmEvent, FsmEventQueue, FsmEventQueueSub, FsmOutputs, FsmOutputsSub,
+    FsmRegionId, FsmRejectedEvents, FsmRejectedEventsSub, FsmResult, FsmTransitionKind,
+    FsmTransitionTaken, Inspect,
 };
 
 pub struct DispatchContext<'a, 'b, 'c, F, Q, I, T>
@@ -15,6 +17,16 @@ where
     pub inspect: &'b mut I,
     pub backend: &'c mut FsmBackendImpl<F>,
     pub timers: &'a mut T,
+    pub outputs: &'a mut dyn FsmOutputs<F>,
+    pub delayed: &'a mut dyn FsmDelayedSender<F>,
+    pub tasks: &'a mut dyn FsmAsyncTasks<F>,
+    /// The reply slot of `dispatch_with_reply`.
+    pub reply: Option<&'a mut dyn Any>,
+    /// The transitions taken by each region, reported in the dispatch outcome.
+    pub transitions: Option<&'a mut [Option<FsmTransitionTaken>]>,
+    /// Keeps the events of the timers that the queue rejected, reported in the outcome of
+    /// `dispatch_timer_events`. The events are dropped if it's `None`.
+    pub rejected: Option<&'a mut dyn FsmRejectedEvents<F>>,
 }
 
 impl<'a, 'b, 'c, F, Q, I, T> DispatchContext<'a, 'b, 'c, F, Q, I, T>
@@ -29,6 +41,26 @@ where
             context: &mut self.backend.context,
             queue: self.queue,
             region,
+            outputs: self.outputs,
+            delayed: self.delayed,
+            tasks: self.tasks,
+            reply: self.reply.as_deref_mut(),
+        }
+    }
+
+    /// Record the transition that this region has taken.
+    pub fn transition_taken(
+        &mut self,
+        region: FsmRegionId,
+        kind: FsmTransitionKind,
+        transition: &'static str,
+    ) {
+        if let Some(slot) = self
+            .transitions
+            .as_deref_mut()
+            .and_then(|t| t.get_mut(region))
+        {
+            *slot = Some(FsmTransitionTaken { kind, transition });
         }
     }
 }
@@ -43,7 +75,11 @@ where
     TFsm: FsmBackend,
     <TFsm as FsmBackend>::States: AsMut<TSubMachine>,
     <TFsm as FsmBackend>::Events: From<<TSubMachine as FsmBackend>::Events>,
+    <TSubMachine as FsmBackend>::Events: TryFrom<<TFsm as FsmBackend>::Events>,
     <TFsm as FsmBackend>::Timers: From<<TSubMachine as FsmBackend>::Timers>,
+    <TFsm as FsmBackend>::Outputs: From<<TSubMachine as FsmBackend>::Outputs>,
+    <TFsm as FsmBackend>::Events: 'static,
+    <TSubMachine as FsmBackend>::Events: 'static,
     TSubMachine: FsmBackend + DerefMut<Target = FsmBackendImpl<TSubMachine>>,
     Q: FsmEventQueue<TFsm>,
     I: Inspect,
@@ -63,6 +99,29 @@ where
         _sub_fsm: core::marker::PhantomData::<TSubMachine>,
     };
 
+    let mut outputs_adapter = FsmOutputsSub {
+        parent: ctx.outputs,
+        _sub_fsm: core::marker::PhantomData::<TSubMachine>,
+    };
+
+    let mut delayed_adapter = FsmDelayedSenderSub {
+        parent: ctx.delayed,
+        _sub_fsm: core::marker::PhantomData::<TSubMachine>,
+    };
+
+    let mut tasks_adapter = FsmAsyncTasksSub {
+        parent: ctx.tasks,
+        _sub_fsm: core::marker::PhantomData::<TSubMachine>,
+    };
+
+    let mut rejected_adapter = ctx
+        .rejected
+        .as_deref_mut()
+        .map(|parent| FsmRejectedEventsSub {
+            parent,
+            _sub_fsm: core::marker::PhantomData::<TSubMachine>,
+        });
+
     let mut inspect = inspect_event_ctx.for_sub_machine::<TSubMachine>();
 
     let sub_dispatch_ctx = DispatchContext {
@@ -70,6 +129,14 @@ where
         inspect: &mut inspect,
         queue: &mut queue_adapter,
         timers: &mut timers_adapter,
+        outputs: &mut outputs_adapter,
+        delayed: &mut delayed_adapter,
+        tasks: &mut tasks_adapter,
+        reply: ctx.reply.as_deref_mut(),
+        transitions: None,
+        rejected: rejected_adapter
+            .as_mut()
+            .map(|r| r as &mut dyn FsmRejectedEvents<TSubMachine>),
     };
 
     <TSubMachine>::dispatch_event(sub_dispatch_ctx, ev)
@@ -1,10 +1,19 @@
-use crate::{lib::*, FsmBackend, FsmEventQueueSender};
+use crate::{
+    lib::*, FsmAsyncTasks, FsmBackend, FsmDelayedSender, FsmEnqueueResult, FsmEventQueueSender,
+    FsmOutputs, FsmResult,
+};
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
 /// The internal event type that also allows stopping or starting the machine.
 #[derive(Clone)]
 pub enum FsmEvent<E, T> {
     Start,
     Stop,
+    /// Evaluates the conditions of the change transitions, dispatched after every
+    /// run-to-completion step.
+    Change,
     Timer(T),
     Event(E),
 }
@@ -24,6 +33,7 @@ where
         match self {
             FsmEvent::Start => f.write_str("Fsm::Start"),
             FsmEvent::Stop => f.write_str("Fsm::Stop"),
+            FsmEvent::Change => f.write_str("Fsm::Change"),
             FsmEvent::Timer(t) => f.write_fmt(format_args!("Fsm::Timer({:?})", t)),
             FsmEvent::Event(ev) => ev.fmt(f),
         }
@@ -38,6 +48,7 @@ where
         match self {
             FsmEvent::Start => "Fsm::Start",
             FsmEvent::Stop => "Fsm::Stop",
+            FsmEvent::Change => "Fsm::Change",
             FsmEvent::Timer(_) => "Fsm::Timer",
             FsmEvent::Event(e) => e.as_ref(),
         }
@@ -56,6 +67,7 @@ impl<E, T> FsmEvent<E, T> {
         match self {
             FsmEvent::Start => FsmEvent::Start,
             FsmEvent::Stop => FsmEvent::Stop,
+            FsmEvent::Change => FsmEvent::Change,
             FsmEvent::Timer(t) => FsmEvent::Timer(t.into()),
             FsmEvent::Event(ev) => FsmEvent::Timer(ev.into()),
         }
@@ -73,6 +85,62 @@ where
     pub context: &'a mut TFsm::Context,
     pub queue: &'a mut Q,
     pub region: FsmRegionId,
+    pub outputs: &'a mut dyn FsmOutputs<TFsm>,
+    /// The slots of the delayed events, see `enqueue_after`.
+    pub delayed: &'a mut dyn FsmDelayedSender<TFsm>,
+    /// The futures of the async actions, see `spawn_async`.
+    pub tasks: &'a mut dyn FsmAsyncTasks<TFsm>,
+    /// The reply slot, set only while dispatching with `dispatch_with_reply`.
+    pub reply: Option<&'a mut dyn Any>,
+}
+
+impl<'a, TFsm, Q> EventContext<'a, TFsm, Q>
+where
+    TFsm: FsmBackend,
+    Q: FsmEventQueueSender<TFsm>,
+{
+    /// Emit an output into the frontend's output sink.
+    pub fn emit<O: Into<<TFsm as FsmBackend>::Outputs>>(&mut self, output: O) -> FsmResult<()> {
+        self.outputs.emit(output.into())
+    }
+
+    /// Enqueue the event after the delay, using a timer of the frontend. Fails with the event
+    /// if all of the slots for the delayed events are taken, or with `FsmError::NotSupported`
+    /// if the machine wasn't built with `fsm.delayed_events::<N>()`.
+    pub fn enqueue_after<E: Into<<TFsm as FsmBackend>::Events>>(
+        &mut self,
+        delay: Duration,
+        event: E,
+    ) -> FsmEnqueueResult<TFsm> {
+        self.delayed.enqueue_after(delay, event.into())
+    }
+
+    /// Run the future once the step is done. Used by the async actions and entry handlers,
+    /// the future can't borrow the context and completes with an optional event. Only the
+    /// async dispatch awaits the futures, the sync one fails with `FsmError::NotSupported`.
+    #[cfg(feature = "alloc")]
+    pub fn spawn_async<T>(&mut self, task: T)
+    where
+        T: Future<Output = Option<<TFsm as FsmBackend>::Events>> + Send + 'static,
+    {
+        self.tasks.spawn(alloc::boxed::Box::pin(task))
+    }
+
+    /// Reply to the caller of `dispatch_with_reply`. Returns `false` if the event wasn't
+    /// dispatched with a reply of this type, or if there was already a reply.
+    pub fn reply<R: 'static>(&mut self, value: R) -> bool {
+        match self
+            .reply
+            .as_deref_mut()
+            .and_then(|r| r.downcast_mut::<Option<R>>())
+        {
+            Some(slot @ None) => {
+                *slot = Some(value);
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
 impl<'a, TFsm, Q> Deref for EventContext<'a, TFsm, Q>
@@ -1,9 +1,12 @@
 use crate::{
-    FsmBackend, FsmBackendImpl, FsmEventQueue, FsmFrontend, FsmResult, FsmTimers, Inspect,
+    FsmBackend, FsmBackendImpl, FsmDelayedEvents, FsmEventQueue, FsmEventQueueInternal,
+    FsmFrontend, FsmOutputs, FsmOutputsNull, FsmResult, FsmStepLimit, FsmTimers, Inspect,
 };
 
+#[cfg(feature = "alloc")]
+use crate::FsmAsyncTasksQueue;
 #[cfg(feature = "std")]
-use crate::{timers::std::TimersStd, FsmEventQueueVec};
+use crate::{timers::std::TimersStd, FsmEventQueueVec, FsmOutputsVec};
 
 /// Builds a frontend for running your FSM.
 pub trait FsmFactory {
@@ -25,18 +28,71 @@ pub trait FsmFactory {
         Q: FsmEventQueue<Self::Fsm>,
         I: Inspect,
         T: FsmTimers<Self::Fsm>,
+    {
+        Self::new_with_outputs(context, queue, inspect, timers, FsmOutputsNull)
+    }
+
+    /// Build a new frontend for the FSM with all the environmental services provided by the caller,
+    /// including the sink for the outputs of the actions.
+    fn new_with_outputs<Q, I, T, O>(
+        context: <Self::Fsm as FsmBackend>::Context,
+        queue: Q,
+        inspect: I,
+        timers: T,
+        outputs: O,
+    ) -> FsmResult<FsmFrontend<Self::Fsm, Q, I, T, O>>
+    where
+        Q: FsmEventQueue<Self::Fsm>,
+        I: Inspect,
+        T: FsmTimers<Self::Fsm>,
+        O: FsmOutputs<Self::Fsm>,
+    {
+        Self::new_with_internal_queue(
+            context,
+            queue,
+            FsmEventQueueInternal::new(),
+            inspect,
+            timers,
+            outputs,
+        )
+    }
+
+    /// Build a new frontend for the FSM with all the environmental services provided by the caller,
+    /// including the queue for the events that are enqueued by the actions. The `queue` receives
+    /// only the events from the outside world.
+    fn new_with_internal_queue<Q, QI, I, T, O>(
+        context: <Self::Fsm as FsmBackend>::Context,
+        queue: Q,
+        internal_queue: QI,
+        inspect: I,
+        timers: T,
+        outputs: O,
+    ) -> FsmResult<FsmFrontend<Self::Fsm, Q, I, T, O, QI>>
+    where
+        Q: FsmEventQueue<Self::Fsm>,
+        QI: FsmEventQueue<Self::Fsm>,
+        I: Inspect,
+        T: FsmTimers<Self::Fsm>,
+        O: FsmOutputs<Self::Fsm>,
     {
         let frontend = FsmFrontend {
             queue,
+            internal_queue,
             inspect,
             backend: FsmBackendImpl::new(context)?,
             timers,
+            outputs,
+            step_limit: FsmStepLimit::default(),
+            delayed: FsmDelayedEvents::new(),
+            #[cfg(feature = "alloc")]
+            tasks: FsmAsyncTasksQueue::new(),
         };
 
         Ok(frontend)
     }
 
-    /// Build a new frontend for the FSM with a `FsmEventQueueVec` queue, `TimersStd` for timers and no logging.
+    /// Build a new frontend for the FSM with `FsmEventQueueVec` queues, `TimersStd` for timers,
+    /// `FsmOutputsVec` for the outputs and no logging.
     #[cfg(feature = "std")]
     fn new(
         context: <Self::Fsm as FsmBackend>::Context,
@@ -46,15 +102,22 @@ pub trait FsmFactory {
             FsmEventQueueVec<Self::Fsm>,
             crate::inspect::null::InspectNull,
             TimersStd<Self::Fsm>,
+            FsmOutputsVec<Self::Fsm>,
         >,
     > {
         use crate::inspect::null::InspectNull;
 
         let frontend = FsmFrontend {
             queue: FsmEventQueueVec::new(),
+            internal_queue: FsmEventQueueInternal::new(),
             backend: FsmBackendImpl::new(context)?,
             inspect: InspectNull::new(),
             timers: TimersStd::new(),
+            outputs: FsmOutputsVec::new(),
+            step_limit: FsmStepLimit::default(),
+            delayed: FsmDelayedEvents::new(),
+            #[cfg(feature = "alloc")]
+            tasks: FsmAsyncTasksQueue::new(),
         };
 
         Ok(frontend)
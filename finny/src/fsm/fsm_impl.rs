@@ -1,6 +1,17 @@
 use crate::{lib::*, DispatchContext, FsmTimers, Inspect};
-use crate::{FsmBackend, FsmEvent, FsmEventQueue, FsmResult, FsmStates};
+use crate::{
+    FsmBackend, FsmDelayedEvents, FsmDispatchOutcome, FsmError, FsmEvent, FsmEventQueue,
+    FsmEventQueueInternal, FsmEventQueueIter, FsmEventQueueShared, FsmEventSender, FsmOutputs,
+    FsmOutputsNull, FsmQueuedEventError, FsmRejectedEvents, FsmResult, FsmStates,
+    FsmStatesTryAsRef, FsmStepLimit, FsmTransitionTaken,
+};
 
+#[cfg(not(feature = "alloc"))]
+use crate::FsmAsyncTasksNull;
+#[cfg(feature = "alloc")]
+use crate::FsmAsyncTasksQueue;
+
+use super::steps::FsmSteps;
 use super::FsmStateFactory;
 
 /// The struct that holds the core context and state of the given Finny FSM. Doesn't include
@@ -33,12 +44,22 @@ impl<F: FsmBackend> FsmBackendImpl<F> {
         self.current_states
     }
 
+    /// Panics if the state isn't stored, see `try_get_state`.
     pub fn get_state<S>(&self) -> &S
     where
         <F as FsmBackend>::States: AsRef<S>,
     {
         self.states.as_ref()
     }
+
+    /// The state, or `None` if it isn't stored. With the enum storage of `states_storage_enum`
+    /// only the active states are stored.
+    pub fn try_get_state<S>(&self) -> Option<&S>
+    where
+        <F as FsmBackend>::States: FsmStatesTryAsRef<S>,
+    {
+        self.states.try_as_ref()
+    }
 }
 
 impl<F: FsmBackend> Deref for FsmBackendImpl<F> {
@@ -63,51 +84,161 @@ pub trait FsmBackendResetSubmachine<F: FsmBackend, FSub> {
 
 /// The frontend of a state machine which also includes environmental services like queues
 /// and inspection. The usual way to use the FSM.
-pub struct FsmFrontend<F, Q, I, T>
+///
+/// The events from the outside world are enqueued into `queue`, while the events enqueued by
+/// the actions land in `internal_queue`. The internal queue is always drained before the next
+/// external event is dequeued.
+pub struct FsmFrontend<F, Q, I, T, O = FsmOutputsNull, QI = FsmEventQueueInternal<F>>
 where
     F: FsmBackend,
     Q: FsmEventQueue<F>,
     I: Inspect,
     T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
 {
     pub backend: FsmBackendImpl<F>,
     pub queue: Q,
+    pub internal_queue: QI,
     pub inspect: I,
     pub timers: T,
+    /// The outputs emitted by the actions, to be drained by the caller.
+    pub outputs: O,
+    /// Bounds the number of microsteps of a single dispatch. Unlimited by default.
+    pub step_limit: FsmStepLimit,
+    /// The events delayed by the actions, waiting for their timers.
+    pub delayed: FsmDelayedEvents<F>,
+    /// The futures of the async actions, awaited by `dispatch_async`.
+    #[cfg(feature = "alloc")]
+    pub tasks: FsmAsyncTasksQueue<F>,
 }
 
-impl<F, Q, I, T> FsmFrontend<F, Q, I, T>
+impl<F, Q, I, T, O, QI> FsmFrontend<F, Q, I, T, O, QI>
 where
     F: FsmBackend,
     Q: FsmEventQueue<F>,
     I: Inspect,
     T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
 {
-    /// Start the FSM, initiates the transition to the initial state.
+    /// Start the FSM, initiates the transition to the initial state. The change transitions
+    /// of the initial states are evaluated once it's done.
     pub fn start(&mut self) -> FsmResult<()> {
-        Self::dispatch_single_event(self, FsmEvent::Start)
+        let mut steps = FsmSteps::new(self.step_limit);
+        Self::dispatch_single_event(self, FsmEvent::Start)?;
+        self.dispatch_change_events_with(&mut steps)
+    }
+
+    /// Stop the FSM, exits the active state of every region and cancels its timers. The
+    /// events that were delayed by the actions are dropped. Stopping a stopped FSM is a no-op.
+    pub fn stop(&mut self) -> FsmResult<()> {
+        let result = Self::dispatch_single_event(self, FsmEvent::Stop);
+        // the delayed events are dropped even if the machine failed to stop
+        let cancelled = self.delayed.cancel_timers(&mut self.timers);
+        result.and(cancelled)
     }
 
     /// Dispatch any pending timer events into the queue, then run all the
-    /// events from the queue until completition.
-    pub fn dispatch_timer_events(&mut self) -> FsmResult<()> {
+    /// events from the queue until completition. The events of the triggered timers count
+    /// as dequeued events of the outcome, so their errors are kept in its `queue_errors`.
+    /// The events that the queues rejected are returned in its `rejected_events`.
+    pub fn dispatch_timer_events(&mut self) -> FsmResult<FsmDispatchOutcome<F>> {
+        let mut steps = FsmSteps::new(self.step_limit);
+        let mut outcome = FsmDispatchOutcome::new(self.backend.current_states);
+
         while let Some(timer_id) = self.timers.get_triggered_timer() {
-            self.dispatch_single_event(FsmEvent::Timer(timer_id))?;
+            if let Some(slot) = F::delayed_slot(&timer_id) {
+                // the delayed events arrive like the ones from the outside world
+                if let Some(ev) = self.delayed.take(slot) {
+                    if let Err((_, ev)) = self.queue.try_enqueue(ev) {
+                        outcome.reject(ev);
+                    }
+                }
+                continue;
+            }
+
+            let ev = FsmEvent::Timer(timer_id);
+            steps.step(&ev, self.backend.current_states)?;
+
+            let index = outcome.queued_events;
+            outcome.queued_events += 1;
+
+            if let Err(error) = Self::dispatch_event_with(self, ev, None, None, Some(&mut outcome))
+            {
+                outcome.push_queue_error(FsmQueuedEventError { index, error });
+            }
+            self.dispatch_change_events_with(&mut steps)?;
         }
 
-        self.dispatch_queue()
+        self.dispatch_queue_with(&mut outcome, &mut steps)?;
+        Ok(outcome)
+    }
+
+    /// Dispatch this event and run it to completition. The outcome lists the transitions that
+    /// handled the event and the errors of the events that were queued in the meantime.
+    ///
+    /// Fails with `FsmError::StepLimitExceeded` if the run exceeds the frontend's step limit.
+    /// The remaining events are kept in the queue.
+    pub fn dispatch<E>(&mut self, event: E) -> FsmResult<FsmDispatchOutcome<F>>
+    where
+        E: Into<<F as FsmBackend>::Events>,
+    {
+        let mut steps = FsmSteps::new(self.step_limit);
+        let mut outcome = FsmDispatchOutcome::<F>::new(self.backend.current_states);
+
+        let ev = event.into();
+        let ev = FsmEvent::Event(ev);
+        steps.step(&ev, self.backend.current_states)?;
+        Self::dispatch_event_with(self, ev, None, Some(outcome.transitions.as_mut()), None)?;
+        self.dispatch_change_events_with(&mut steps)?;
+
+        self.dispatch_queue_with(&mut outcome, &mut steps)?;
+        Ok(outcome)
     }
 
-    /// Dispatch this event and run it to completition.
-    pub fn dispatch<E>(&mut self, event: E) -> FsmResult<()>
+    /// Dispatch this event and run it to completition. The action that handles the event replies
+    /// with `ctx.reply(value)`. Only the actions of this event can reply, not the actions of the
+    /// events that were queued in the meantime. Like `dispatch`, the queues are left as they are
+    /// if the event itself fails.
+    pub fn dispatch_with_reply<E, R>(&mut self, event: E) -> FsmResult<R>
     where
         E: Into<<F as FsmBackend>::Events>,
+        R: 'static,
     {
+        let mut steps = FsmSteps::new(self.step_limit);
+        let mut outcome = FsmDispatchOutcome::new(self.backend.current_states);
+        let mut reply: Option<R> = None;
+
         let ev = event.into();
         let ev = FsmEvent::Event(ev);
-        Self::dispatch_single_event(self, ev)?;
+        steps.step(&ev, self.backend.current_states)?;
+        Self::dispatch_event_with(self, ev, Some(&mut reply), None, None)?;
+        self.dispatch_change_events_with(&mut steps)?;
+        self.dispatch_queue_with(&mut outcome, &mut steps)?;
+
+        reply.ok_or(FsmError::NoReply)
+    }
 
-        self.dispatch_queue()
+    /// Evaluate the change transitions once and fire the ones whose conditions are met. The
+    /// frontend already does this at the end of every run-to-completion step.
+    pub fn dispatch_change_events(&mut self) -> FsmResult<()> {
+        let mut steps = FsmSteps::new(self.step_limit);
+        self.dispatch_change_events_with(&mut steps)
+    }
+
+    /// The change transitions are evaluated once per step, so a condition that stays true in
+    /// the target state fires again only after the next step.
+    fn dispatch_change_events_with(&mut self, steps: &mut FsmSteps<F>) -> FsmResult<()> {
+        if !F::has_change_events() {
+            return Ok(());
+        }
+
+        steps.step(&FsmEvent::Change, self.backend.current_states)?;
+        match Self::dispatch_single_event(self, FsmEvent::Change) {
+            Ok(()) | Err(FsmError::NoTransition) => Ok(()),
+            Err(e) => Err(e),
+        }
     }
 
     /// Dispatch only this event, do not run it to completition.
@@ -115,34 +246,260 @@ where
         &mut self,
         event: FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
     ) -> FsmResult<()> {
+        Self::dispatch_event_with(self, event, None, None, None)
+    }
+
+    fn dispatch_event_with(
+        &mut self,
+        event: FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
+        reply: Option<&mut dyn Any>,
+        transitions: Option<&mut [Option<FsmTransitionTaken>]>,
+        rejected: Option<&mut dyn FsmRejectedEvents<F>>,
+    ) -> FsmResult<()> {
+        #[cfg(feature = "alloc")]
+        let tasks = &mut self.tasks;
+        #[cfg(not(feature = "alloc"))]
+        let tasks = &mut FsmAsyncTasksNull;
+
         let dispatch_ctx = DispatchContext {
             backend: &mut self.backend,
             inspect: &mut self.inspect,
-            queue: &mut self.queue,
+            queue: &mut self.internal_queue,
             timers: &mut self.timers,
+            outputs: &mut self.outputs,
+            delayed: &mut self.delayed,
+            tasks,
+            reply,
+            transitions,
+            rejected: rejected.map(|r| r as &mut dyn FsmRejectedEvents<F>),
+        };
+
+        let result = F::dispatch_event(dispatch_ctx, event);
+        self.delayed
+            .start_timers(&mut self.timers, &mut self.inspect);
+        result?;
+
+        // the futures of the async actions that nothing would await
+        #[cfg(feature = "alloc")]
+        self.tasks.check_rejected()?;
+
+        Ok(())
+    }
+
+    /// Dispatch both of the event queues and run them to completition. The errors of the
+    /// dequeued events are reported in the outcome.
+    pub fn dispatch_queue(&mut self) -> FsmResult<FsmDispatchOutcome<F>> {
+        let mut steps = FsmSteps::new(self.step_limit);
+        let mut outcome = FsmDispatchOutcome::new(self.backend.current_states);
+        self.dispatch_queue_with(&mut outcome, &mut steps)?;
+        Ok(outcome)
+    }
+
+    fn dispatch_queue_with(
+        &mut self,
+        outcome: &mut FsmDispatchOutcome<F>,
+        steps: &mut FsmSteps<F>,
+    ) -> FsmResult<()> {
+        while self.dispatch_next_queued(outcome, steps)? {}
+
+        outcome.states_after = self.backend.current_states;
+        outcome.delayed_events_dropped += self.delayed.take_dropped();
+        Ok(())
+    }
+
+    /// Dispatch the next queued event, returns `false` once both of the queues are empty.
+    fn dispatch_next_queued(
+        &mut self,
+        outcome: &mut FsmDispatchOutcome<F>,
+        steps: &mut FsmSteps<F>,
+    ) -> FsmResult<bool> {
+        if self.internal_queue.len() == 0 && self.queue.len() == 0 {
+            return Ok(false);
+        }
+        // an aborted dispatch keeps the event in the queue
+        steps.check()?;
+
+        // the follow-ups of the actions run before the next external event
+        let ev = match self
+            .internal_queue
+            .dequeue()
+            .or_else(|| self.queue.dequeue())
+        {
+            Some(ev) => ev,
+            None => return Ok(false),
         };
 
-        F::dispatch_event(dispatch_ctx, event)
+        let ev = FsmEvent::Event(ev);
+        steps.record(&ev, self.backend.current_states);
+
+        let index = outcome.queued_events;
+        outcome.queued_events += 1;
+
+        if let Err(error) = Self::dispatch_single_event(self, ev) {
+            outcome.push_queue_error(FsmQueuedEventError { index, error });
+        }
+        self.dispatch_change_events_with(steps)?;
+
+        Ok(true)
+    }
+
+    /// Start the FSM and await the async entry handlers of the initial states, then run the
+    /// queued events to completion like `dispatch_async`.
+    #[cfg(feature = "alloc")]
+    pub async fn start_async(&mut self) -> FsmResult<FsmDispatchOutcome<F>> {
+        self.tasks.set_awaited(true);
+        let dispatch = FsmAsyncDispatch { fsm: self };
+        dispatch.fsm.start_async_with().await
+    }
+
+    #[cfg(feature = "alloc")]
+    async fn start_async_with(&mut self) -> FsmResult<FsmDispatchOutcome<F>> {
+        let mut steps = FsmSteps::new(self.step_limit);
+        let mut outcome = FsmDispatchOutcome::<F>::new(self.backend.current_states);
+
+        Self::dispatch_event_with(
+            self,
+            FsmEvent::Start,
+            None,
+            Some(outcome.transitions.as_mut()),
+            None,
+        )?;
+        self.dispatch_change_events_with(&mut steps)?;
+        self.run_to_completion_async(&mut outcome, &mut steps)
+            .await?;
+        Ok(outcome)
+    }
+
+    /// Dispatch this event and run it to completion, awaiting the futures of the async actions
+    /// and entry handlers. Each step's futures are awaited before the next event is dispatched
+    /// and the events they complete with are dispatched first, so the events that are enqueued
+    /// in the meantime wait in the queues.
+    #[cfg(feature = "alloc")]
+    pub async fn dispatch_async<E>(&mut self, event: E) -> FsmResult<FsmDispatchOutcome<F>>
+    where
+        E: Into<<F as FsmBackend>::Events>,
+    {
+        self.tasks.set_awaited(true);
+        let dispatch = FsmAsyncDispatch { fsm: self };
+        dispatch.fsm.dispatch_async_with(event.into()).await
     }
 
-    /// Dispatch the entire event queue and run it to completition.
-    pub fn dispatch_queue(&mut self) -> FsmResult<()> {
-        while let Some(ev) = self.queue.dequeue() {
-            let ev: <F as FsmBackend>::Events = ev;
-            // todo: log?
-            let _ = Self::dispatch(self, ev);
+    #[cfg(feature = "alloc")]
+    async fn dispatch_async_with(
+        &mut self,
+        event: <F as FsmBackend>::Events,
+    ) -> FsmResult<FsmDispatchOutcome<F>> {
+        let mut steps = FsmSteps::new(self.step_limit);
+        let mut outcome = FsmDispatchOutcome::<F>::new(self.backend.current_states);
+
+        let ev = FsmEvent::Event(event);
+        steps.step(&ev, self.backend.current_states)?;
+        Self::dispatch_event_with(self, ev, None, Some(outcome.transitions.as_mut()), None)?;
+        self.dispatch_change_events_with(&mut steps)?;
+
+        self.run_to_completion_async(&mut outcome, &mut steps)
+            .await?;
+        Ok(outcome)
+    }
+
+    #[cfg(feature = "alloc")]
+    async fn run_to_completion_async(
+        &mut self,
+        outcome: &mut FsmDispatchOutcome<F>,
+        steps: &mut FsmSteps<F>,
+    ) -> FsmResult<()> {
+        loop {
+            self.run_tasks(outcome).await;
+            if !self.dispatch_next_queued(outcome, steps)? {
+                break;
+            }
         }
 
+        outcome.states_after = self.backend.current_states;
         Ok(())
     }
+
+    /// Await the spawned futures in order, their events go into the internal queue. The events
+    /// that it rejects are returned in the outcome.
+    #[cfg(feature = "alloc")]
+    async fn run_tasks(&mut self, outcome: &mut FsmDispatchOutcome<F>) {
+        while let Some(task) = self.tasks.next() {
+            if let Some(ev) = task.await {
+                if let Err((_, ev)) = self.internal_queue.try_enqueue(ev) {
+                    outcome.reject(ev);
+                }
+            }
+        }
+    }
+
+    /// Drop all of the pending events of both queues.
+    pub fn clear_queue(&mut self) {
+        self.internal_queue.clear();
+        self.queue.clear();
+    }
+
+    /// Keep only the pending events of both queues for which the predicate returns `true`.
+    /// Both of the queues are filtered, the first error is returned, see `FsmEventQueue::retain`.
+    pub fn retain_queue<P>(&mut self, mut keep: P) -> FsmResult<()>
+    where
+        P: FnMut(&<F as FsmBackend>::Events) -> bool,
+    {
+        let internal = self.internal_queue.retain(&mut keep);
+        let external = self.queue.retain(keep);
+        internal.and(external)
+    }
+}
+
+impl<F, Q, I, T, O, QI> FsmFrontend<F, Q, I, T, O, QI>
+where
+    F: FsmBackend,
+    Q: FsmEventQueueIter<F>,
+    I: Inspect,
+    T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueueIter<F>,
+{
+    /// The event that would be dispatched next from the queues.
+    pub fn peek_queue(&self) -> Option<&<F as FsmBackend>::Events> {
+        self.internal_queue.peek().or_else(|| self.queue.peek())
+    }
+
+    /// The pending events of both queues, in the order they would be dispatched.
+    pub fn iter_queue(&self) -> impl Iterator<Item = &<F as FsmBackend>::Events> + '_ {
+        self.internal_queue.iter().chain(self.queue.iter())
+    }
 }
 
-impl<F, Q, I, T> Deref for FsmFrontend<F, Q, I, T>
+impl<F, Q, I, T, O, QI> FsmFrontend<F, Q, I, T, O, QI>
+where
+    F: FsmBackend,
+    Q: FsmEventQueueShared<F>,
+    I: Inspect,
+    T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
+{
+    /// A cloneable handle for enqueueing the events into this machine's queue from other
+    /// threads.
+    pub fn sender(&self) -> FsmEventSender<F, Q> {
+        FsmEventSender::new(self.queue.clone())
+    }
+
+    /// Block until an event is enqueued, or until the timeout elapses. Use the time until the
+    /// next timer as the timeout to wait for both. Returns `true` if there is a pending event.
+    pub fn wait_for_events(&self, timeout: Duration) -> bool {
+        self.internal_queue.len() > 0 || self.queue.wait_timeout(timeout)
+    }
+}
+
+impl<F, Q, I, T, O, QI> Deref for FsmFrontend<F, Q, I, T, O, QI>
 where
     F: FsmBackend,
     Q: FsmEventQueue<F>,
     I: Inspect,
     T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
 {
     type Target = FsmBackendImpl<F>;
 
@@ -151,14 +508,46 @@ where
     }
 }
 
-impl<F, Q, I, T> DerefMut for FsmFrontend<F, Q, I, T>
+impl<F, Q, I, T, O, QI> DerefMut for FsmFrontend<F, Q, I, T, O, QI>
 where
     F: FsmBackend,
     Q: FsmEventQueue<F>,
     I: Inspect,
     T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.backend
     }
 }
+
+/// Held by the async dispatch while it runs. The tasks are reset once it's done, failed or its
+/// future was dropped, so the tasks that weren't awaited don't outlive it.
+#[cfg(feature = "alloc")]
+struct FsmAsyncDispatch<'a, F, Q, I, T, O, QI>
+where
+    F: FsmBackend,
+    Q: FsmEventQueue<F>,
+    I: Inspect,
+    T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
+{
+    fsm: &'a mut FsmFrontend<F, Q, I, T, O, QI>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, F, Q, I, T, O, QI> Drop for FsmAsyncDispatch<'a, F, Q, I, T, O, QI>
+where
+    F: FsmBackend,
+    Q: FsmEventQueue<F>,
+    I: Inspect,
+    T: FsmTimers<F>,
+    O: FsmOutputs<F>,
+    QI: FsmEventQueue<F>,
+{
+    fn drop(&mut self) {
+        self.fsm.tasks.reset();
+    }
+}
@@ -1,24 +1,34 @@
 //! The public Finite State Machine traits. The derive macros will implement these for your particular
 //! state machines.
 
+mod delayed;
 mod dispatch;
 mod events;
 mod fsm_factory;
 mod fsm_impl;
 mod inspect;
+mod outcome;
+mod outputs;
 mod queue;
 mod states;
+mod steps;
+mod tasks;
 mod tests_fsm;
 mod timers;
 mod transitions;
 
+pub use self::delayed::*;
 pub use self::dispatch::*;
 pub use self::events::*;
 pub use self::fsm_factory::*;
 pub use self::fsm_impl::*;
 pub use self::inspect::*;
+pub use self::outcome::*;
+pub use self::outputs::*;
 pub use self::queue::*;
 pub use self::states::*;
+pub use self::steps::*;
+pub use self::tasks::*;
 pub use self::timers::*;
 pub use self::transitions::*;
 
@@ -33,6 +43,12 @@ pub enum FsmError {
     QueueOverCapacity,
     NotSupported,
     TimerNotStarted,
+    /// The event was handled, but none of the actions replied with a value of the requested type.
+    NoReply,
+    /// The dispatch didn't run to completion within the frontend's step limit.
+    StepLimitExceeded(FsmStepLimitExceeded),
+    /// The thread of the actor has stopped.
+    ActorStopped,
 }
 
 pub type FsmDispatchResult = FsmResult<()>;
@@ -47,11 +63,27 @@ where
     type Context;
     /// The type that holds the states of the machine.
     type States: FsmStates<Self>;
-    /// A tagged union type with all the supported events. This type has to support cloning to facilitate
-    /// the dispatch into sub-machines and into multiple regions.
-    type Events: AsRef<str> + Clone;
-    /// An enum with variants for all the possible timer instances, with support for submachines.
+    /// A tagged union type with all the supported events. The events are matched by reference in
+    /// the regions and moved into the sub-machines, so cloning isn't required. The generated
+    /// enum is `Clone` if all of the event types are.
+    type Events: AsRef<str>;
+    /// An enum with variants for all the possible timer instances, with support for submachines
+    /// and the slots of the delayed events.
     type Timers: Debug + Clone + PartialEq + AllVariants;
+    /// The outputs that the actions emit into the frontend's output sink.
+    type Outputs;
+    /// The slots of the events delayed by the actions, none unless the machine was built with
+    /// `fsm.delayed_events::<N>()`.
+    type DelayedSlots: FsmDelayedSlots<Self>;
+
+    /// The name of the event, same as its `AsRef<str>` implementation.
+    fn event_name(event: &Self::Events) -> &'static str;
+
+    /// The slot of the delayed event, if this is one of our delayed events' timers.
+    fn delayed_slot(timer: &Self::Timers) -> Option<FsmDelayedSlot>;
+
+    /// The timer of the delayed event's slot, if the machine delays events.
+    fn delayed_timer(slot: FsmDelayedSlot) -> Option<Self::Timers>;
 
     fn dispatch_event<Q, I, T>(
         ctx: DispatchContext<Self, Q, I, T>,
@@ -61,6 +93,12 @@ where
         Q: FsmEventQueue<Self>,
         I: Inspect,
         T: FsmTimers<Self>;
+
+    /// Does this machine, or any of its submachines, have transitions triggered by a
+    /// change in the context?
+    fn has_change_events() -> bool {
+        false
+    }
 }
 
 /// Enumerates all the possible variants of a simple enum.
@@ -0,0 +1,184 @@
+use crate::lib::*;
+use crate::{FsmBackend, FsmError, FsmRegionId, FsmRejectedEvents, FsmStates};
+
+/// The maximum number of the queued events' errors that are kept in the dispatch outcome.
+pub const FSM_OUTCOME_MAX_QUEUE_ERRORS: usize = 16;
+
+/// The kind of the transition that handled the event in a region.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FsmTransitionKind {
+    /// The region was started and entered its initial state.
+    Start,
+    /// The region exited its state and entered another one.
+    State,
+    /// The action was executed without exiting the state.
+    Internal,
+    /// The state was exited and entered again.
+    SelfTransition,
+    /// The event was handled by the active sub-machine.
+    SubMachine,
+    /// The region exited its state and was stopped.
+    Stop,
+}
+
+/// The transition that was taken by a region.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FsmTransitionTaken {
+    pub kind: FsmTransitionKind,
+    /// The type name of the transition, or of the sub-machine.
+    pub transition: &'static str,
+}
+
+/// The error of an event that was dequeued and dispatched in the same run.
+#[derive(Debug, PartialEq)]
+pub struct FsmQueuedEventError {
+    /// The position of the event among the dequeued events, starting with zero.
+    pub index: usize,
+    pub error: FsmError,
+}
+
+/// What happened during a dispatch, including the events that were queued by the actions and
+/// processed in the same run.
+pub struct FsmDispatchOutcome<F: FsmBackend> {
+    /// The transition taken by each region for the dispatched event. `None` if the region
+    /// didn't handle it.
+    pub transitions: <<F as FsmBackend>::States as FsmStates<F>>::RegionTransitions,
+    /// The current states before the event was dispatched.
+    pub states_before: <<F as FsmBackend>::States as FsmStates<F>>::CurrentState,
+    /// The current states after the queue was processed.
+    pub states_after: <<F as FsmBackend>::States as FsmStates<F>>::CurrentState,
+    /// The number of the events that were dequeued and dispatched.
+    pub queued_events: usize,
+    /// The errors of the dequeued events. Only the first `FSM_OUTCOME_MAX_QUEUE_ERRORS` are kept.
+    pub queue_errors: heapless::Vec<FsmQueuedEventError, FSM_OUTCOME_MAX_QUEUE_ERRORS>,
+    /// The number of the errors that didn't fit into `queue_errors`.
+    pub queue_errors_dropped: usize,
+    /// The number of the delayed events that were dropped because their timers couldn't be
+    /// created.
+    pub delayed_events_dropped: usize,
+    /// The events that the queues rejected: the events of the triggered timers, the delayed
+    /// events and the events of the awaited async actions. Only the first
+    /// `FSM_OUTCOME_MAX_QUEUE_ERRORS` are kept.
+    pub rejected_events: heapless::Vec<<F as FsmBackend>::Events, FSM_OUTCOME_MAX_QUEUE_ERRORS>,
+    /// The number of the rejected events that didn't fit into `rejected_events`.
+    pub rejected_events_dropped: usize,
+}
+
+impl<F: FsmBackend> FsmDispatchOutcome<F> {
+    pub fn new(states_before: <<F as FsmBackend>::States as FsmStates<F>>::CurrentState) -> Self {
+        FsmDispatchOutcome {
+            transitions: Default::default(),
+            states_before,
+            states_after: states_before,
+            queued_events: 0,
+            queue_errors: heapless::Vec::new(),
+            queue_errors_dropped: 0,
+            delayed_events_dropped: 0,
+            rejected_events: heapless::Vec::new(),
+            rejected_events_dropped: 0,
+        }
+    }
+
+    /// Keep the error of a dequeued event, or count it once `queue_errors` is full.
+    pub(crate) fn push_queue_error(&mut self, error: FsmQueuedEventError) {
+        if self.queue_errors.push(error).is_err() {
+            self.queue_errors_dropped += 1;
+        }
+    }
+
+    /// The transition taken by this region, if any.
+    pub fn transition(&self, region: FsmRegionId) -> Option<FsmTransitionTaken> {
+        self.transitions.as_ref().get(region).copied().flatten()
+    }
+
+    /// Did any of the regions change its current state?
+    pub fn states_changed(&self) -> bool {
+        self.states_before != self.states_after
+    }
+}
+
+impl<F: FsmBackend> Debug for FsmDispatchOutcome<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FsmDispatchOutcome")
+            .field("transitions", &self.transitions)
+            .field("states_before", &self.states_before)
+            .field("states_after", &self.states_after)
+            .field("queued_events", &self.queued_events)
+            .field("queue_errors", &self.queue_errors)
+            .field("queue_errors_dropped", &self.queue_errors_dropped)
+            .field("delayed_events_dropped", &self.delayed_events_dropped)
+            .field("rejected_events", &RejectedNames(&self.rejected_events))
+            .field("rejected_events_dropped", &self.rejected_events_dropped)
+            .finish()
+    }
+}
+
+impl<F: FsmBackend> PartialEq for FsmDispatchOutcome<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.transitions == other.transitions
+            && self.states_before == other.states_before
+            && self.states_after == other.states_after
+            && self.queued_events == other.queued_events
+            && self.queue_errors == other.queue_errors
+            && self.queue_errors_dropped == other.queue_errors_dropped
+            && self.delayed_events_dropped == other.delayed_events_dropped
+            // the events don't have to be comparable, only their names are
+            && self
+                .rejected_events
+                .iter()
+                .map(AsRef::as_ref)
+                .eq(other.rejected_events.iter().map(AsRef::as_ref))
+            && self.rejected_events_dropped == other.rejected_events_dropped
+    }
+}
+
+impl<F: FsmBackend> FsmRejectedEvents<F> for FsmDispatchOutcome<F> {
+    fn reject(&mut self, event: <F as FsmBackend>::Events) {
+        if self.rejected_events.push(event).is_err() {
+            self.rejected_events_dropped += 1;
+        }
+    }
+}
+
+/// Pass the error of the dispatch, or the errors of its dequeued events, to the callback.
+#[cfg(feature = "std")]
+pub(crate) fn report_errors<F: FsmBackend>(
+    result: crate::FsmResult<FsmDispatchOutcome<F>>,
+    on_error: &mut dyn FnMut(FsmError),
+) {
+    match result {
+        Ok(outcome) => outcome
+            .queue_errors
+            .into_iter()
+            .for_each(|e| on_error(e.error)),
+        Err(e) => on_error(e),
+    }
+}
+
+/// Lists the names of the rejected events, the events don't have to implement `Debug`.
+struct RejectedNames<'a, E>(&'a [E]);
+
+impl<'a, E: AsRef<str>> Debug for RejectedNames<'a, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(AsRef::as_ref))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+use super::tests_fsm::TestFsm;
+
+#[test]
+fn test_queue_errors_dropped() {
+    let mut outcome = FsmDispatchOutcome::<TestFsm>::new(Default::default());
+    for index in 0..FSM_OUTCOME_MAX_QUEUE_ERRORS + 2 {
+        outcome.push_queue_error(FsmQueuedEventError {
+            index,
+            error: FsmError::NoTransition,
+        });
+    }
+
+    assert_eq!(FSM_OUTCOME_MAX_QUEUE_ERRORS, outcome.queue_errors.len());
+    assert_eq!(2, outcome.queue_errors_dropped);
+}
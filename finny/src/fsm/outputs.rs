@@ -0,0 +1,105 @@
+use crate::lib::*;
+use crate::{FsmBackend, FsmResult};
+
+/// The sink for the outputs that the actions emit with `ctx.emit(output)`. The caller of the
+/// frontend collects them after the dispatch.
+pub trait FsmOutputs<F: FsmBackend> {
+    /// Try to emit an output.
+    fn emit(&mut self, output: <F as FsmBackend>::Outputs) -> FsmResult<()>;
+}
+
+#[cfg(feature = "std")]
+mod outputs_vec {
+    use super::*;
+
+    /// An unbound buffer of outputs that uses `Vec`.
+    pub struct FsmOutputsVec<F: FsmBackend> {
+        outputs: Vec<<F as FsmBackend>::Outputs>,
+    }
+
+    impl<F: FsmBackend> FsmOutputsVec<F> {
+        pub fn new() -> Self {
+            FsmOutputsVec {
+                outputs: Vec::new(),
+            }
+        }
+
+        /// Take all of the buffered outputs, in the order they were emitted.
+        pub fn drain(&mut self) -> impl Iterator<Item = <F as FsmBackend>::Outputs> + '_ {
+            self.outputs.drain(..)
+        }
+
+        /// The buffered outputs.
+        pub fn as_slice(&self) -> &[<F as FsmBackend>::Outputs] {
+            &self.outputs
+        }
+
+        /// Number of the buffered outputs.
+        pub fn len(&self) -> usize {
+            self.outputs.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.outputs.is_empty()
+        }
+    }
+
+    impl<F: FsmBackend> Default for FsmOutputsVec<F> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<F: FsmBackend> FsmOutputs<F> for FsmOutputsVec<F> {
+        fn emit(&mut self, output: <F as FsmBackend>::Outputs) -> FsmResult<()> {
+            self.outputs.push(output);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::outputs_vec::*;
+
+/// Discards all of the outputs.
+pub struct FsmOutputsNull;
+
+impl<F: FsmBackend> FsmOutputs<F> for FsmOutputsNull {
+    fn emit(&mut self, _output: <F as FsmBackend>::Outputs) -> FsmResult<()> {
+        Ok(())
+    }
+}
+
+/// Used to funnel the outputs of the sub-machine up to the parent's sink.
+pub struct FsmOutputsSub<'a, F, FSub>
+where
+    F: FsmBackend,
+{
+    pub parent: &'a mut dyn FsmOutputs<F>,
+    pub _sub_fsm: PhantomData<FSub>,
+}
+
+impl<'a, F, FSub> FsmOutputs<FSub> for FsmOutputsSub<'a, F, FSub>
+where
+    F: FsmBackend,
+    FSub: FsmBackend,
+    <F as FsmBackend>::Outputs: From<<FSub as FsmBackend>::Outputs>,
+{
+    fn emit(&mut self, output: <FSub as FsmBackend>::Outputs) -> FsmResult<()> {
+        self.parent.emit(output.into())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+use super::tests_fsm::TestFsm;
+
+#[test]
+#[cfg(feature = "std")]
+fn test_outputs_vec() {
+    let mut outputs = FsmOutputsVec::<TestFsm>::new();
+    outputs.emit(()).unwrap();
+    outputs.emit(()).unwrap();
+    assert_eq!(2, outputs.len());
+    assert_eq!(2, outputs.drain().count());
+    assert!(outputs.is_empty());
+}
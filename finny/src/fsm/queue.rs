@@ -1,5 +1,5 @@
 use crate::lib::*;
-use crate::{FsmBackend, FsmResult};
+use crate::{FsmBackend, FsmError, FsmResult};
 
 /// The event queueing trait for FSMs. Can be used from outside or from within the actions of the FSM.
 pub trait FsmEventQueue<F: FsmBackend>: FsmEventQueueSender<F> {
@@ -7,11 +7,123 @@ pub trait FsmEventQueue<F: FsmBackend>: FsmEventQueueSender<F> {
     fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events>;
     /// Number of messages to be dequeued.
     fn len(&self) -> usize;
+
+    /// Drop all of the pending events.
+    fn clear(&mut self) {
+        while self.dequeue().is_some() {}
+    }
+
+    /// Keep only the pending events for which the predicate returns `true`, in their order.
+    /// Unless the queue overrides it, the pending events are rotated through the queue.
+    ///
+    /// The rotation of a queue that is shared with other producers can lose the kept events:
+    /// the room freed by each dequeued event can be taken by a new event before the kept one
+    /// is enqueued again. Those events are dropped and the call fails with the error of the
+    /// queue, once all of the pending events were rotated.
+    fn retain<P: FnMut(&<F as FsmBackend>::Events) -> bool>(
+        &mut self,
+        mut keep: P,
+    ) -> FsmResult<()> {
+        let mut result = Ok(());
+        for _ in 0..self.len() {
+            match self.dequeue() {
+                Some(ev) if keep(&ev) => {
+                    if let Err((e, _)) = self.try_enqueue(ev) {
+                        result = result.and(Err(e));
+                    }
+                }
+                Some(_) => (),
+                None => break,
+            }
+        }
+        result
+    }
+}
+
+/// Implemented by the queues whose pending events can be borrowed.
+pub trait FsmEventQueueIter<F: FsmBackend>: FsmEventQueue<F> {
+    type Iter<'a>: Iterator<Item = &'a <F as FsmBackend>::Events>
+    where
+        Self: 'a,
+        <F as FsmBackend>::Events: 'a;
+
+    /// The event that would be dequeued next.
+    fn peek(&self) -> Option<&<F as FsmBackend>::Events>;
+    /// The pending events, in the order they would be dequeued.
+    fn iter(&self) -> Self::Iter<'_>;
+}
+
+/// Implemented by the queues whose clones can be fed from other threads, and which can wake
+/// the dispatching thread when an event arrives.
+pub trait FsmEventQueueShared<F: FsmBackend>: FsmEventQueue<F> + Clone + Send {
+    /// Block until there is a pending event, or until the timeout elapses. Returns `true` if
+    /// there is a pending event.
+    fn wait_timeout(&self, timeout: Duration) -> bool;
+}
+
+/// A cloneable handle for enqueueing the events into a shared queue from other threads. The
+/// thread that waits on the queue is woken up by every event.
+pub struct FsmEventSender<F, Q>
+where
+    F: FsmBackend,
+    Q: FsmEventQueueShared<F>,
+{
+    queue: Q,
+    _fsm: PhantomData<fn() -> F>,
+}
+
+impl<F, Q> FsmEventSender<F, Q>
+where
+    F: FsmBackend,
+    Q: FsmEventQueueShared<F>,
+{
+    pub fn new(queue: Q) -> Self {
+        FsmEventSender {
+            queue,
+            _fsm: PhantomData,
+        }
+    }
+}
+
+impl<F, Q> Clone for FsmEventSender<F, Q>
+where
+    F: FsmBackend,
+    Q: FsmEventQueueShared<F>,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.queue.clone())
+    }
+}
+
+impl<F, Q> FsmEventQueueSender<F> for FsmEventSender<F, Q>
+where
+    F: FsmBackend,
+    Q: FsmEventQueueShared<F>,
+{
+    fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmEnqueueResult<F> {
+        self.queue.try_enqueue(event)
+    }
 }
 
 pub trait FsmEventQueueSender<F: FsmBackend> {
     /// Try to enqueue an event.
-    fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()>;
+    fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()> {
+        self.try_enqueue(event).map_err(|(e, _)| e)
+    }
+
+    /// Try to enqueue an event. A rejected event is returned along with the error, so that the
+    /// caller can retry it later, store it elsewhere or slow down the producer.
+    fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmEnqueueResult<F>;
+}
+
+/// The result of `FsmEventQueueSender::try_enqueue`, with the rejected event on failure.
+pub type FsmEnqueueResult<F> = Result<(), (FsmError, <F as FsmBackend>::Events)>;
+
+/// The priority of the event in the priority queues. Events with a higher priority are dequeued
+/// first, the ones with the same priority in the order they were enqueued. Generated from the
+/// `#[priority(n)]` attributes in the builder, zero by default.
+pub trait FsmEventPriority {
+    fn priority(&self) -> i32;
 }
 
 #[cfg(feature = "std")]
@@ -39,10 +151,41 @@ mod queue_vec {
         fn len(&self) -> usize {
             self.queue.len()
         }
+
+        fn clear(&mut self) {
+            self.queue.clear();
+        }
+
+        fn retain<P: FnMut(&<F as FsmBackend>::Events) -> bool>(
+            &mut self,
+            keep: P,
+        ) -> FsmResult<()> {
+            self.queue.retain(keep);
+            Ok(())
+        }
+    }
+
+    impl<F: FsmBackend> FsmEventQueueIter<F> for FsmEventQueueVec<F> {
+        type Iter<'a>
+            = std::collections::vec_deque::Iter<'a, <F as FsmBackend>::Events>
+        where
+            Self: 'a,
+            <F as FsmBackend>::Events: 'a;
+
+        fn peek(&self) -> Option<&<F as FsmBackend>::Events> {
+            self.queue.front()
+        }
+
+        fn iter(&self) -> Self::Iter<'_> {
+            self.queue.iter()
+        }
     }
 
     impl<F: FsmBackend> FsmEventQueueSender<F> for FsmEventQueueVec<F> {
-        fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()> {
+        fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+            &mut self,
+            event: E,
+        ) -> FsmEnqueueResult<F> {
             self.queue.push_back(event.into());
             Ok(())
         }
@@ -54,9 +197,7 @@ pub use self::queue_vec::*;
 
 #[cfg(feature = "std")]
 mod queue_vec_shared {
-    use std::sync::{Arc, Mutex};
-
-    use crate::FsmError;
+    use std::sync::{Arc, Condvar, Mutex};
 
     use super::*;
 
@@ -73,6 +214,7 @@ mod queue_vec_shared {
             Self {
                 inner: Inner {
                     queue: self.inner.queue.clone(),
+                    arrived: self.inner.arrived.clone(),
                 },
             }
         }
@@ -80,6 +222,7 @@ mod queue_vec_shared {
 
     struct Inner<F: FsmBackend> {
         queue: Arc<Mutex<VecDeque<<F as FsmBackend>::Events>>>,
+        arrived: Arc<Condvar>,
     }
 
     impl<F: FsmBackend> FsmEventQueueVecShared<F> {
@@ -87,11 +230,32 @@ mod queue_vec_shared {
             let q = VecDeque::new();
             let inner = Inner {
                 queue: Arc::new(Mutex::new(q)),
+                arrived: Arc::new(Condvar::new()),
             };
             FsmEventQueueVecShared { inner }
         }
     }
 
+    impl<F> FsmEventQueueShared<F> for FsmEventQueueVecShared<F>
+    where
+        F: FsmBackend,
+        <F as FsmBackend>::Events: Send,
+    {
+        fn wait_timeout(&self, timeout: Duration) -> bool {
+            match self.inner.queue.lock() {
+                Ok(q) => match self
+                    .inner
+                    .arrived
+                    .wait_timeout_while(q, timeout, |q| q.is_empty())
+                {
+                    Ok((q, _)) => !q.is_empty(),
+                    Err(_) => false,
+                },
+                Err(_) => false,
+            }
+        }
+    }
+
     impl<F: FsmBackend> FsmEventQueue<F> for FsmEventQueueVecShared<F> {
         fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events> {
             if let Ok(mut q) = self.inner.queue.lock() {
@@ -108,15 +272,37 @@ mod queue_vec_shared {
                 0
             }
         }
+
+        fn clear(&mut self) {
+            if let Ok(mut q) = self.inner.queue.lock() {
+                q.clear();
+            }
+        }
+
+        fn retain<P: FnMut(&<F as FsmBackend>::Events) -> bool>(
+            &mut self,
+            keep: P,
+        ) -> FsmResult<()> {
+            if let Ok(mut q) = self.inner.queue.lock() {
+                q.retain(keep);
+            }
+            Ok(())
+        }
     }
 
     impl<F: FsmBackend> FsmEventQueueSender<F> for FsmEventQueueVecShared<F> {
-        fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()> {
+        fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+            &mut self,
+            event: E,
+        ) -> FsmEnqueueResult<F> {
+            let event = event.into();
             if let Ok(mut q) = self.inner.queue.lock() {
-                q.push_back(event.into());
+                q.push_back(event);
+                drop(q);
+                self.inner.arrived.notify_all();
                 Ok(())
             } else {
-                Err(FsmError::QueueOverCapacity)
+                Err((FsmError::QueueOverCapacity, event))
             }
         }
     }
@@ -125,6 +311,203 @@ mod queue_vec_shared {
 #[cfg(feature = "std")]
 pub use self::queue_vec_shared::*;
 
+#[cfg(feature = "std")]
+mod queue_bounded {
+    use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+    use std::time::Instant;
+
+    use super::*;
+
+    /// A bounded event queue that can be shared between threads. The producers can block
+    /// or wait with a timeout until there is room in the queue, and the dispatching thread
+    /// can wait for the events with `dequeue_timeout`.
+    ///
+    /// The methods of the queue traits never block.
+    pub struct FsmEventQueueBounded<F: FsmBackend> {
+        inner: Arc<Inner<F>>,
+    }
+
+    impl<F> Clone for FsmEventQueueBounded<F>
+    where
+        F: FsmBackend,
+    {
+        fn clone(&self) -> Self {
+            Self {
+                inner: self.inner.clone(),
+            }
+        }
+    }
+
+    struct Inner<F: FsmBackend> {
+        queue: Mutex<VecDeque<<F as FsmBackend>::Events>>,
+        not_empty: Condvar,
+        not_full: Condvar,
+        capacity: usize,
+    }
+
+    impl<F: FsmBackend> FsmEventQueueBounded<F> {
+        pub fn new(capacity: usize) -> Self {
+            let inner = Inner {
+                queue: Mutex::new(VecDeque::with_capacity(capacity)),
+                not_empty: Condvar::new(),
+                not_full: Condvar::new(),
+                capacity,
+            };
+            FsmEventQueueBounded {
+                inner: Arc::new(inner),
+            }
+        }
+
+        pub fn capacity(&self) -> usize {
+            self.inner.capacity
+        }
+
+        /// Enqueue the event, blocking while the queue is full.
+        pub fn enqueue_blocking<E: Into<<F as FsmBackend>::Events>>(
+            &self,
+            event: E,
+        ) -> FsmResult<()> {
+            let mut q = self.lock()?;
+            while q.len() >= self.inner.capacity {
+                q = self
+                    .inner
+                    .not_full
+                    .wait(q)
+                    .map_err(|_| FsmError::QueueOverCapacity)?;
+            }
+            self.push(q, event.into());
+            Ok(())
+        }
+
+        /// Enqueue the event, waiting at most `timeout` for room in the queue. The event is
+        /// returned if the queue is still full.
+        pub fn enqueue_timeout<E: Into<<F as FsmBackend>::Events>>(
+            &self,
+            event: E,
+            timeout: Duration,
+        ) -> FsmEnqueueResult<F> {
+            let event = event.into();
+            let deadline = Instant::now() + timeout;
+            let mut q = match self.lock() {
+                Ok(q) => q,
+                Err(e) => return Err((e, event)),
+            };
+            while q.len() >= self.inner.capacity {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err((FsmError::QueueOverCapacity, event));
+                }
+                q = match self.inner.not_full.wait_timeout(q, deadline - now) {
+                    Ok((q, _)) => q,
+                    Err(_) => return Err((FsmError::QueueOverCapacity, event)),
+                };
+            }
+            self.push(q, event);
+            Ok(())
+        }
+
+        /// Dequeue an event, waiting at most `timeout` for one to arrive. Use the time until
+        /// the next timer as the timeout to wait for both.
+        pub fn dequeue_timeout(&self, timeout: Duration) -> Option<<F as FsmBackend>::Events> {
+            let deadline = Instant::now() + timeout;
+            let mut q = self.lock().ok()?;
+            loop {
+                if let Some(ev) = q.pop_front() {
+                    self.inner.not_full.notify_one();
+                    return Some(ev);
+                }
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                q = self.inner.not_empty.wait_timeout(q, deadline - now).ok()?.0;
+            }
+        }
+
+        fn lock(&self) -> FsmResult<MutexGuard<'_, VecDeque<<F as FsmBackend>::Events>>> {
+            self.inner
+                .queue
+                .lock()
+                .map_err(|_| FsmError::QueueOverCapacity)
+        }
+
+        fn push(
+            &self,
+            mut q: MutexGuard<'_, VecDeque<<F as FsmBackend>::Events>>,
+            event: <F as FsmBackend>::Events,
+        ) {
+            q.push_back(event);
+            drop(q);
+            self.inner.not_empty.notify_all();
+        }
+    }
+
+    impl<F> FsmEventQueueShared<F> for FsmEventQueueBounded<F>
+    where
+        F: FsmBackend,
+        <F as FsmBackend>::Events: Send,
+    {
+        fn wait_timeout(&self, timeout: Duration) -> bool {
+            match self.lock() {
+                Ok(q) => match self
+                    .inner
+                    .not_empty
+                    .wait_timeout_while(q, timeout, |q| q.is_empty())
+                {
+                    Ok((q, _)) => !q.is_empty(),
+                    Err(_) => false,
+                },
+                Err(_) => false,
+            }
+        }
+    }
+
+    impl<F: FsmBackend> FsmEventQueue<F> for FsmEventQueueBounded<F> {
+        fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events> {
+            self.dequeue_timeout(Duration::ZERO)
+        }
+
+        fn len(&self) -> usize {
+            match self.lock() {
+                Ok(q) => q.len(),
+                Err(_) => 0,
+            }
+        }
+
+        fn clear(&mut self) {
+            if let Ok(mut q) = self.lock() {
+                q.clear();
+                drop(q);
+                self.inner.not_full.notify_all();
+            }
+        }
+
+        fn retain<P: FnMut(&<F as FsmBackend>::Events) -> bool>(
+            &mut self,
+            keep: P,
+        ) -> FsmResult<()> {
+            if let Ok(mut q) = self.lock() {
+                q.retain(keep);
+                drop(q);
+                self.inner.not_full.notify_all();
+            }
+            Ok(())
+        }
+    }
+
+    impl<F: FsmBackend> FsmEventQueueSender<F> for FsmEventQueueBounded<F> {
+        fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+            &mut self,
+            event: E,
+        ) -> FsmEnqueueResult<F> {
+            self.enqueue_timeout(event, Duration::ZERO)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::queue_bounded::*;
+
 mod queue_array {
     use arraydeque::ArrayDeque;
 
@@ -163,16 +546,50 @@ mod queue_array {
         fn len(&self) -> usize {
             self.dequeue.len()
         }
+
+        fn clear(&mut self) {
+            self.dequeue.clear();
+        }
+
+        fn retain<P: FnMut(&<F as FsmBackend>::Events) -> bool>(
+            &mut self,
+            keep: P,
+        ) -> FsmResult<()> {
+            self.dequeue.retain(keep);
+            Ok(())
+        }
+    }
+
+    impl<F, const CAP: usize> FsmEventQueueIter<F> for FsmEventQueueArray<F, CAP>
+    where
+        F: FsmBackend,
+    {
+        type Iter<'a>
+            = arraydeque::Iter<'a, <F as FsmBackend>::Events>
+        where
+            Self: 'a,
+            <F as FsmBackend>::Events: 'a;
+
+        fn peek(&self) -> Option<&<F as FsmBackend>::Events> {
+            self.dequeue.front()
+        }
+
+        fn iter(&self) -> Self::Iter<'_> {
+            self.dequeue.iter()
+        }
     }
 
     impl<F, const CAP: usize> FsmEventQueueSender<F> for FsmEventQueueArray<F, CAP>
     where
         F: FsmBackend,
     {
-        fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()> {
+        fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+            &mut self,
+            event: E,
+        ) -> FsmEnqueueResult<F> {
             match self.dequeue.push_back(event.into()) {
                 Ok(_) => Ok(()),
-                Err(_) => Err(crate::FsmError::QueueOverCapacity),
+                Err(e) => Err((FsmError::QueueOverCapacity, e.element)),
             }
         }
     }
@@ -180,25 +597,419 @@ mod queue_array {
 
 pub use self::queue_array::*;
 
+/// The internal queue of the frontends that were built without one, for the events enqueued by
+/// the actions.
+#[cfg(feature = "std")]
+pub type FsmEventQueueInternal<F> = FsmEventQueueVec<F>;
+
+/// The internal queue of the frontends that were built without one, a heapless queue of 16
+/// events without `std`.
+#[cfg(not(feature = "std"))]
+pub type FsmEventQueueInternal<F> = FsmEventQueueArray<F, 16>;
+
+mod queue_priority {
+    use core::cmp::Ordering;
+
+    use heapless::binary_heap::{BinaryHeap, Max};
+
+    use super::*;
+
+    /// A queued event, ordered by its priority and then by the order of arrival. The order of
+    /// arrival is a `u64` on every target, so that it doesn't wrap around in practice.
+    pub(crate) struct PriorityEntry<E> {
+        pub priority: i32,
+        pub seq: u64,
+        pub event: E,
+    }
+
+    impl<E> PartialEq for PriorityEntry<E> {
+        fn eq(&self, other: &Self) -> bool {
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl<E> Eq for PriorityEntry<E> {}
+
+    impl<E> PartialOrd for PriorityEntry<E> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl<E> Ord for PriorityEntry<E> {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.priority
+                .cmp(&other.priority)
+                .then_with(|| other.seq.cmp(&self.seq))
+        }
+    }
+
+    /// A heapless priority queue with a fixed size. The priorities come from the
+    /// `FsmEventPriority` trait, or from the function given to `with_priority`.
+    pub struct FsmEventQueuePriorityArray<F, const CAP: usize>
+    where
+        F: FsmBackend,
+    {
+        heap: BinaryHeap<PriorityEntry<<F as FsmBackend>::Events>, Max, CAP>,
+        priority: fn(&<F as FsmBackend>::Events) -> i32,
+        seq: u64,
+    }
+
+    impl<F, const CAP: usize> FsmEventQueuePriorityArray<F, CAP>
+    where
+        F: FsmBackend,
+        <F as FsmBackend>::Events: FsmEventPriority,
+    {
+        pub fn new() -> Self {
+            Self::with_priority(<<F as FsmBackend>::Events as FsmEventPriority>::priority)
+        }
+    }
+
+    impl<F, const CAP: usize> Default for FsmEventQueuePriorityArray<F, CAP>
+    where
+        F: FsmBackend,
+        <F as FsmBackend>::Events: FsmEventPriority,
+    {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<F, const CAP: usize> FsmEventQueuePriorityArray<F, CAP>
+    where
+        F: FsmBackend,
+    {
+        pub fn with_priority(priority: fn(&<F as FsmBackend>::Events) -> i32) -> Self {
+            Self {
+                heap: BinaryHeap::new(),
+                priority,
+                seq: 0,
+            }
+        }
+    }
+
+    impl<F, const CAP: usize> FsmEventQueue<F> for FsmEventQueuePriorityArray<F, CAP>
+    where
+        F: FsmBackend,
+    {
+        fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events> {
+            self.heap.pop().map(|e| e.event)
+        }
+
+        fn len(&self) -> usize {
+            self.heap.len()
+        }
+
+        fn clear(&mut self) {
+            self.heap.clear();
+        }
+    }
+
+    impl<F, const CAP: usize> FsmEventQueueSender<F> for FsmEventQueuePriorityArray<F, CAP>
+    where
+        F: FsmBackend,
+    {
+        fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+            &mut self,
+            event: E,
+        ) -> FsmEnqueueResult<F> {
+            let event = event.into();
+            let entry = PriorityEntry {
+                priority: (self.priority)(&event),
+                seq: self.seq,
+                event,
+            };
+            match self.heap.push(entry) {
+                Ok(_) => {
+                    self.seq = self.seq.wrapping_add(1);
+                    Ok(())
+                }
+                Err(entry) => Err((FsmError::QueueOverCapacity, entry.event)),
+            }
+        }
+    }
+}
+
+pub use self::queue_priority::*;
+
+#[cfg(feature = "std")]
+mod queue_priority_std {
+    use std::collections::BinaryHeap;
+
+    use super::queue_priority::PriorityEntry;
+    use super::*;
+
+    /// An unbound priority queue that uses `BinaryHeap`. The priorities come from the
+    /// `FsmEventPriority` trait, or from the function given to `with_priority`.
+    pub struct FsmEventQueuePriority<F: FsmBackend> {
+        heap: BinaryHeap<PriorityEntry<<F as FsmBackend>::Events>>,
+        priority: fn(&<F as FsmBackend>::Events) -> i32,
+        seq: u64,
+    }
+
+    impl<F> FsmEventQueuePriority<F>
+    where
+        F: FsmBackend,
+        <F as FsmBackend>::Events: FsmEventPriority,
+    {
+        pub fn new() -> Self {
+            Self::with_priority(<<F as FsmBackend>::Events as FsmEventPriority>::priority)
+        }
+    }
+
+    impl<F> Default for FsmEventQueuePriority<F>
+    where
+        F: FsmBackend,
+        <F as FsmBackend>::Events: FsmEventPriority,
+    {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<F: FsmBackend> FsmEventQueuePriority<F> {
+        pub fn with_priority(priority: fn(&<F as FsmBackend>::Events) -> i32) -> Self {
+            FsmEventQueuePriority {
+                heap: BinaryHeap::new(),
+                priority,
+                seq: 0,
+            }
+        }
+    }
+
+    impl<F: FsmBackend> FsmEventQueue<F> for FsmEventQueuePriority<F> {
+        fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events> {
+            self.heap.pop().map(|e| e.event)
+        }
+
+        fn len(&self) -> usize {
+            self.heap.len()
+        }
+
+        fn clear(&mut self) {
+            self.heap.clear();
+        }
+
+        fn retain<P: FnMut(&<F as FsmBackend>::Events) -> bool>(
+            &mut self,
+            mut keep: P,
+        ) -> FsmResult<()> {
+            self.heap.retain(|e| keep(&e.event));
+            Ok(())
+        }
+    }
+
+    impl<F: FsmBackend> FsmEventQueueSender<F> for FsmEventQueuePriority<F> {
+        fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+            &mut self,
+            event: E,
+        ) -> FsmEnqueueResult<F> {
+            let event = event.into();
+            self.heap.push(PriorityEntry {
+                priority: (self.priority)(&event),
+                seq: self.seq,
+                event,
+            });
+            self.seq = self.seq.wrapping_add(1);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::queue_priority_std::*;
+
+mod queue_coalescing {
+    use core::mem::discriminant;
+
+    use super::*;
+
+    type IsDuplicate<F> = fn(&<F as FsmBackend>::Events, &<F as FsmBackend>::Events) -> bool;
+
+    /// An adapter that merges the pending events of the inner queue. Every enqueued event is
+    /// compared with all of the pending ones, so only use it with short queues that aren't
+    /// shared with other producers.
+    pub struct FsmEventQueueCoalescing<F, Q>
+    where
+        F: FsmBackend,
+        Q: FsmEventQueue<F>,
+    {
+        queue: Q,
+        keep_latest: Option<fn(&<F as FsmBackend>::Events) -> bool>,
+        is_duplicate: Option<IsDuplicate<F>>,
+        dropped: usize,
+        _fsm: PhantomData<F>,
+    }
+
+    impl<F, Q> FsmEventQueueCoalescing<F, Q>
+    where
+        F: FsmBackend,
+        Q: FsmEventQueue<F>,
+    {
+        pub fn new(queue: Q) -> Self {
+            FsmEventQueueCoalescing {
+                queue,
+                keep_latest: None,
+                is_duplicate: None,
+                dropped: 0,
+                _fsm: PhantomData,
+            }
+        }
+
+        /// Keep only the latest pending instance of the events of the variants that are
+        /// selected by this function. The new event takes the place of the pending one.
+        ///
+        /// Example: `.keep_latest(|ev| matches!(ev, RobotEvents::PositionUpdate(_)))`
+        pub fn keep_latest(mut self, variants: fn(&<F as FsmBackend>::Events) -> bool) -> Self {
+            self.keep_latest = Some(variants);
+            self
+        }
+
+        /// Drop the new event if this function finds a duplicate among the pending ones.
+        /// The arguments are the pending and the new event.
+        pub fn drop_duplicates(mut self, is_duplicate: IsDuplicate<F>) -> Self {
+            self.is_duplicate = Some(is_duplicate);
+            self
+        }
+
+        /// The number of the pending events that the inner queue rejected while they were
+        /// rotated through it, since the last call. Only happens if the inner queue is fed by
+        /// other producers in the meantime.
+        pub fn take_dropped(&mut self) -> usize {
+            core::mem::take(&mut self.dropped)
+        }
+
+        pub fn inner(&self) -> &Q {
+            &self.queue
+        }
+
+        pub fn into_inner(self) -> Q {
+            self.queue
+        }
+    }
+
+    impl<F, Q> FsmEventQueue<F> for FsmEventQueueCoalescing<F, Q>
+    where
+        F: FsmBackend,
+        Q: FsmEventQueue<F>,
+    {
+        fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events> {
+            self.queue.dequeue()
+        }
+
+        fn len(&self) -> usize {
+            self.queue.len()
+        }
+
+        fn clear(&mut self) {
+            self.queue.clear();
+        }
+
+        fn retain<P: FnMut(&<F as FsmBackend>::Events) -> bool>(
+            &mut self,
+            keep: P,
+        ) -> FsmResult<()> {
+            self.queue.retain(keep)
+        }
+    }
+
+    impl<F, Q> FsmEventQueueIter<F> for FsmEventQueueCoalescing<F, Q>
+    where
+        F: FsmBackend,
+        Q: FsmEventQueueIter<F>,
+    {
+        type Iter<'a>
+            = Q::Iter<'a>
+        where
+            Self: 'a,
+            <F as FsmBackend>::Events: 'a;
+
+        fn peek(&self) -> Option<&<F as FsmBackend>::Events> {
+            self.queue.peek()
+        }
+
+        fn iter(&self) -> Self::Iter<'_> {
+            self.queue.iter()
+        }
+    }
+
+    impl<F, Q> FsmEventQueueSender<F> for FsmEventQueueCoalescing<F, Q>
+    where
+        F: FsmBackend,
+        Q: FsmEventQueue<F>,
+    {
+        fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+            &mut self,
+            event: E,
+        ) -> FsmEnqueueResult<F> {
+            let event = event.into();
+            let keep_latest = self.keep_latest.is_some_and(|f| f(&event));
+            if !keep_latest && self.is_duplicate.is_none() {
+                return self.queue.try_enqueue(event);
+            }
+
+            // rotate the pending events through the inner queue, merging the new one
+            let mut event = Some(event);
+            let mut merged = false;
+            for _ in 0..self.queue.len() {
+                let pending = match self.queue.dequeue() {
+                    Some(pending) => pending,
+                    None => break,
+                };
+
+                let (next, is_new) = match event {
+                    Some(ref ev)
+                        if keep_latest && !merged && discriminant(&pending) == discriminant(ev) =>
+                    {
+                        merged = true;
+                        (event.take().unwrap(), true)
+                    }
+                    Some(ref ev) if self.is_duplicate.is_some_and(|f| f(&pending, ev)) => {
+                        event = None;
+                        (pending, false)
+                    }
+                    _ => (pending, false),
+                };
+
+                // keep rotating, the caller gets the new event back if it doesn't fit
+                if let Err((_, next)) = self.queue.try_enqueue(next) {
+                    if is_new {
+                        event = Some(next);
+                    } else {
+                        self.dropped += 1;
+                    }
+                }
+            }
+
+            match event {
+                Some(ev) => self.queue.try_enqueue(ev),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+pub use self::queue_coalescing::*;
+
+#[cfg(feature = "alloc")]
 pub mod heapless_shared {
     //! A heapless queue with Clone and Arc support.
 
     use core::sync::atomic::{AtomicUsize, Ordering};
 
-    use crate::FsmError;
-
     use super::*;
 
     extern crate alloc;
     use alloc::sync::Arc;
-    use heapless::mpmc::Q64;
+    use heapless::mpmc::MpMcQueue;
 
-    /// An unbound event queue that uses `VecDeque`.
-    pub struct FsmEventQueueHeaplessShared<F: FsmBackend> {
-        inner: Arc<Inner<F>>,
+    /// A bounded event queue that uses `heapless::mpmc::MpMcQueue`. The capacity has to be a
+    /// power of two, and at most 128 unless the `mpmc_large` feature is enabled.
+    pub struct FsmEventQueueHeaplessShared<F: FsmBackend, const N: usize = 64> {
+        inner: Arc<Inner<F, N>>,
     }
 
-    impl<F> Clone for FsmEventQueueHeaplessShared<F>
+    impl<F, const N: usize> Clone for FsmEventQueueHeaplessShared<F, N>
     where
         F: FsmBackend,
     {
@@ -209,14 +1020,14 @@ pub mod heapless_shared {
         }
     }
 
-    struct Inner<F: FsmBackend> {
-        queue: Q64<<F as FsmBackend>::Events>,
+    struct Inner<F: FsmBackend, const N: usize> {
+        queue: MpMcQueue<<F as FsmBackend>::Events, N>,
         len: AtomicUsize,
     }
 
-    impl<F: FsmBackend> FsmEventQueueHeaplessShared<F> {
+    impl<F: FsmBackend, const N: usize> FsmEventQueueHeaplessShared<F, N> {
         pub fn new() -> Self {
-            let q = Q64::new();
+            let q = MpMcQueue::new();
             let inner = Inner {
                 queue: q,
                 len: AtomicUsize::new(0),
@@ -227,7 +1038,7 @@ pub mod heapless_shared {
         }
     }
 
-    impl<F: FsmBackend> FsmEventQueue<F> for FsmEventQueueHeaplessShared<F> {
+    impl<F: FsmBackend, const N: usize> FsmEventQueue<F> for FsmEventQueueHeaplessShared<F, N> {
         fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events> {
             match self.inner.queue.dequeue() {
                 Some(e) => {
@@ -243,17 +1054,150 @@ pub mod heapless_shared {
         }
     }
 
-    impl<F: FsmBackend> FsmEventQueueSender<F> for FsmEventQueueHeaplessShared<F> {
-        fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()> {
+    impl<F: FsmBackend, const N: usize> FsmEventQueueSender<F> for FsmEventQueueHeaplessShared<F, N> {
+        fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+            &mut self,
+            event: E,
+        ) -> FsmEnqueueResult<F> {
             match self.inner.queue.enqueue(event.into()) {
                 Ok(_) => {
                     self.inner.len.fetch_add(1, Ordering::SeqCst);
                     Ok(())
                 }
-                Err(_) => Err(FsmError::QueueOverCapacity),
+                Err(event) => Err((FsmError::QueueOverCapacity, event)),
+            }
+        }
+    }
+}
+
+pub mod heapless_static {
+    //! A heapless queue in `'static` storage, for targets without an allocator. The producer
+    //! handles can be used from interrupt handlers.
+    //!
+    //! ```ignore
+    //! static QUEUE: FsmEventQueueStatic<MyFsmEvents, 16> = FsmEventQueueStatic::new();
+    //!
+    //! let (producer, consumer) = QUEUE.split::<MyFsm>();
+    //! ```
+
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    use heapless::mpmc::MpMcQueue;
+
+    /// The storage of the queue, meant to be placed into a `static`. The capacity has to be a
+    /// power of two, and at most 128 unless the `mpmc_large` feature is enabled.
+    pub struct FsmEventQueueStatic<E, const N: usize> {
+        queue: MpMcQueue<E, N>,
+        len: AtomicUsize,
+    }
+
+    impl<E, const N: usize> FsmEventQueueStatic<E, N> {
+        pub const fn new() -> Self {
+            FsmEventQueueStatic {
+                queue: MpMcQueue::new(),
+                len: AtomicUsize::new(0),
+            }
+        }
+
+        /// Split the queue into a producer handle, which can be copied into the interrupt
+        /// handlers, and a consumer handle for the machine's frontend. The handles of a queue
+        /// in a `static` have the `'static` lifetime.
+        pub fn split<F>(
+            &self,
+        ) -> (
+            FsmEventQueueStaticProducer<'_, F, N>,
+            FsmEventQueueStaticConsumer<'_, F, N>,
+        )
+        where
+            F: FsmBackend<Events = E>,
+        {
+            let producer = FsmEventQueueStaticProducer {
+                storage: self,
+                _fsm: PhantomData,
+            };
+            let consumer = FsmEventQueueStaticConsumer {
+                storage: self,
+                _fsm: PhantomData,
+            };
+            (producer, consumer)
+        }
+
+        fn enqueue(&self, event: E) -> Result<(), (FsmError, E)> {
+            match self.queue.enqueue(event) {
+                Ok(_) => {
+                    self.len.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                }
+                Err(event) => Err((FsmError::QueueOverCapacity, event)),
             }
         }
     }
+
+    impl<E, const N: usize> Default for FsmEventQueueStatic<E, N> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// Enqueues the events into the static queue.
+    pub struct FsmEventQueueStaticProducer<'a, F: FsmBackend, const N: usize> {
+        storage: &'a FsmEventQueueStatic<<F as FsmBackend>::Events, N>,
+        _fsm: PhantomData<F>,
+    }
+
+    impl<F: FsmBackend, const N: usize> Clone for FsmEventQueueStaticProducer<'_, F, N> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<F: FsmBackend, const N: usize> Copy for FsmEventQueueStaticProducer<'_, F, N> {}
+
+    impl<F: FsmBackend, const N: usize> FsmEventQueueSender<F>
+        for FsmEventQueueStaticProducer<'_, F, N>
+    {
+        fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+            &mut self,
+            event: E,
+        ) -> FsmEnqueueResult<F> {
+            self.storage.enqueue(event.into())
+        }
+    }
+
+    /// The machine's end of the static queue.
+    pub struct FsmEventQueueStaticConsumer<'a, F: FsmBackend, const N: usize> {
+        storage: &'a FsmEventQueueStatic<<F as FsmBackend>::Events, N>,
+        _fsm: PhantomData<F>,
+    }
+
+    impl<F: FsmBackend, const N: usize> FsmEventQueue<F> for FsmEventQueueStaticConsumer<'_, F, N> {
+        fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events> {
+            match self.storage.queue.dequeue() {
+                Some(e) => {
+                    self.storage.len.fetch_sub(1, Ordering::SeqCst);
+                    Some(e)
+                }
+                None => None,
+            }
+        }
+
+        fn len(&self) -> usize {
+            self.storage.len.load(Ordering::SeqCst)
+        }
+    }
+
+    impl<F: FsmBackend, const N: usize> FsmEventQueueSender<F>
+        for FsmEventQueueStaticConsumer<'_, F, N>
+    {
+        fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+            &mut self,
+            event: E,
+        ) -> FsmEnqueueResult<F> {
+            self.storage.enqueue(event.into())
+        }
+    }
 }
 
 pub struct FsmEventQueueNull<F> {
@@ -279,7 +1223,10 @@ impl<F: FsmBackend> FsmEventQueue<F> for FsmEventQueueNull<F> {
 }
 
 impl<F: FsmBackend> FsmEventQueueSender<F> for FsmEventQueueNull<F> {
-    fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, _event: E) -> FsmResult<()> {
+    fn try_enqueue<E: Into<<F as FsmBackend>::Events>>(
+        &mut self,
+        _event: E,
+    ) -> FsmEnqueueResult<F> {
         Ok(())
     }
 }
@@ -300,6 +1247,7 @@ where
     Q: FsmEventQueueSender<F>,
     FSub: FsmBackend,
     <F as FsmBackend>::Events: From<<FSub as FsmBackend>::Events>,
+    <FSub as FsmBackend>::Events: TryFrom<<F as FsmBackend>::Events>,
 {
     fn dequeue(&mut self) -> Option<<FSub as FsmBackend>::Events> {
         None
@@ -316,9 +1264,22 @@ where
     Q: FsmEventQueueSender<F>,
     FSub: FsmBackend,
     <F as FsmBackend>::Events: From<<FSub as FsmBackend>::Events>,
+    <FSub as FsmBackend>::Events: TryFrom<<F as FsmBackend>::Events>,
 {
-    fn enqueue<E: Into<<FSub as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()> {
-        self.parent.enqueue(event.into())
+    fn try_enqueue<E: Into<<FSub as FsmBackend>::Events>>(
+        &mut self,
+        event: E,
+    ) -> FsmEnqueueResult<FSub> {
+        let event: <F as FsmBackend>::Events = event.into().into();
+        match self.parent.try_enqueue(event) {
+            Ok(()) => Ok(()),
+            Err((e, event)) => match event.try_into() {
+                Ok(event) => Err((e, event)),
+                Err(_) => {
+                    unreachable!("The parent machine's event always wraps the sub-machine's.")
+                }
+            },
+        }
     }
 }
 
@@ -351,10 +1312,265 @@ fn test_dequeue_vec_shared() {
 }
 
 #[test]
+#[cfg(feature = "std")]
+fn test_bounded() {
+    let queue = FsmEventQueueBounded::<TestFsm>::new(16);
+    test_queue(queue);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_bounded_back_pressure() {
+    use super::tests_fsm::{EventA, Events};
+    use std::time::Duration;
+
+    let mut queue = FsmEventQueueBounded::<TestFsm>::new(2);
+    queue.enqueue(EventA { n: 0 }).unwrap();
+    queue.enqueue(EventA { n: 1 }).unwrap();
+    assert!(queue.try_enqueue(EventA { n: 2 }).is_err());
+    match queue.enqueue_timeout(EventA { n: 2 }, Duration::from_millis(10)) {
+        Err((FsmError::QueueOverCapacity, Events::EventA(EventA { n: 2 }))) => (),
+        _ => panic!("Expected the event to be rejected."),
+    }
+
+    // the producer waits for the consumer
+    let producer = {
+        let queue = queue.clone();
+        std::thread::spawn(move || {
+            for n in 2..10 {
+                queue.enqueue_blocking(EventA { n }).unwrap();
+            }
+        })
+    };
+
+    let mut received = vec![];
+    while let Some(ev) = queue.dequeue_timeout(Duration::from_secs(5)) {
+        assert!(queue.len() <= 2);
+        received.push(ev);
+        if received.len() == 10 {
+            break;
+        }
+    }
+    producer.join().unwrap();
+
+    let expected: Vec<_> = (0..10).map(|n| Events::EventA(EventA { n })).collect();
+    assert_eq!(expected, received);
+    assert_eq!(None, queue.dequeue_timeout(Duration::from_millis(10)));
+}
+
+#[test]
+#[cfg(feature = "alloc")]
 fn test_heapless_shared() {
     use self::heapless_shared::FsmEventQueueHeaplessShared;
     let queue = FsmEventQueueHeaplessShared::<TestFsm>::new();
     test_queue(queue);
+    let queue = FsmEventQueueHeaplessShared::<TestFsm, 32>::new();
+    test_queue(queue);
+}
+
+#[test]
+fn test_heapless_static() {
+    use self::heapless_static::FsmEventQueueStatic;
+    use super::tests_fsm::{EventA, Events};
+
+    static QUEUE: FsmEventQueueStatic<Events, 32> = FsmEventQueueStatic::new();
+    let (_, consumer) = QUEUE.split::<TestFsm>();
+    test_queue(consumer);
+
+    static INTERRUPTS: FsmEventQueueStatic<Events, 4> = FsmEventQueueStatic::new();
+    let (producer, mut consumer) = INTERRUPTS.split::<TestFsm>();
+    let mut from_interrupt = producer;
+    from_interrupt.enqueue(EventA { n: 1 }).unwrap();
+    assert_eq!(1, consumer.len());
+    assert_eq!(Some(Events::EventA(EventA { n: 1 })), consumer.dequeue());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_priority() {
+    let queue = FsmEventQueuePriority::<TestFsm>::new();
+    test_queue(queue);
+    let queue = FsmEventQueuePriority::<TestFsm>::with_priority(|ev| ev.priority());
+    test_priority_order(queue);
+}
+
+#[test]
+fn test_priority_array() {
+    let queue = FsmEventQueuePriorityArray::<TestFsm, 16>::new();
+    test_queue(queue);
+    let queue = FsmEventQueuePriorityArray::<TestFsm, 16>::new();
+    test_priority_order(queue);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_coalescing() {
+    use super::tests_fsm::{EventA, Events};
+
+    let queue = FsmEventQueueCoalescing::new(FsmEventQueueVec::<TestFsm>::new());
+    test_queue(queue);
+
+    // only the latest reading fits into the array
+    let mut queue = FsmEventQueueCoalescing::new(FsmEventQueueArray::<TestFsm, 1>::new())
+        .keep_latest(|ev| matches!(ev, Events::EventA(_)));
+    for n in 0..10 {
+        queue.enqueue(EventA { n }).unwrap();
+    }
+    assert_eq!(1, queue.len());
+    assert_eq!(Some(Events::EventA(EventA { n: 9 })), queue.dequeue());
+
+    let mut queue = FsmEventQueueCoalescing::new(FsmEventQueueVec::<TestFsm>::new())
+        .drop_duplicates(|a, b| a == b);
+    for n in [1, 2, 1, 3, 2] {
+        queue.enqueue(EventA { n }).unwrap();
+    }
+    let pending: Vec<_> = core::iter::from_fn(|| queue.dequeue()).collect();
+    assert_eq!(
+        vec![
+            Events::EventA(EventA { n: 1 }),
+            Events::EventA(EventA { n: 2 }),
+            Events::EventA(EventA { n: 3 })
+        ],
+        pending
+    );
+
+    // the full inner queue rejects the new event, the pending ones keep their order
+    let mut queue = FsmEventQueueCoalescing::new(FsmEventQueueArray::<TestFsm, 2>::new())
+        .drop_duplicates(|a, b| a == b);
+    for n in [1, 2] {
+        queue.enqueue(EventA { n }).unwrap();
+    }
+    match queue.try_enqueue(EventA { n: 3 }) {
+        Err((FsmError::QueueOverCapacity, Events::EventA(EventA { n: 3 }))) => (),
+        _ => panic!("Expected the new event to be rejected."),
+    }
+    assert!(queue.try_enqueue(EventA { n: 1 }).is_ok());
+    assert_eq!(0, queue.take_dropped());
+    let pending: Vec<_> = core::iter::from_fn(|| queue.dequeue()).collect();
+    assert_eq!(
+        vec![
+            Events::EventA(EventA { n: 1 }),
+            Events::EventA(EventA { n: 2 })
+        ],
+        pending
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_retain_clear() {
+    test_queue_management(FsmEventQueueVec::<TestFsm>::new());
+    test_queue_management(FsmEventQueueVecShared::<TestFsm>::new());
+    test_queue_management(FsmEventQueueBounded::<TestFsm>::new(16));
+    test_queue_management(FsmEventQueueArray::<TestFsm, 16>::new());
+    test_queue_management(FsmEventQueuePriority::<TestFsm>::new());
+    test_queue_management(FsmEventQueuePriorityArray::<TestFsm, 16>::new());
+    test_queue_management(heapless_shared::FsmEventQueueHeaplessShared::<TestFsm>::new());
+    test_queue_management(FsmEventQueueCoalescing::new(
+        FsmEventQueueVec::<TestFsm>::new(),
+    ));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_retain_shared_producer() {
+    use super::tests_fsm::{EventA, Events};
+
+    let mut queue = heapless_shared::FsmEventQueueHeaplessShared::<TestFsm, 2>::new();
+    let mut producer = queue.clone();
+    for n in 0..2 {
+        queue.enqueue(EventA { n }).unwrap();
+    }
+
+    // the producer takes the room of the first kept event
+    let mut produced = false;
+    let result = queue.retain(|_| {
+        if !produced {
+            produced = true;
+            producer.enqueue(EventA { n: 2 }).unwrap();
+        }
+        true
+    });
+    assert_eq!(Err(FsmError::QueueOverCapacity), result);
+
+    // the first event was dropped, the second one was rotated behind the new one
+    let pending: Vec<_> = core::iter::from_fn(|| queue.dequeue()).collect();
+    assert_eq!(
+        vec![
+            Events::EventA(EventA { n: 2 }),
+            Events::EventA(EventA { n: 1 })
+        ],
+        pending
+    );
+}
+
+#[test]
+fn test_iter() {
+    use super::tests_fsm::{EventA, Events};
+
+    let mut queue = FsmEventQueueArray::<TestFsm, 16>::new();
+    assert_eq!(None, queue.peek());
+    for n in 0..3 {
+        queue.enqueue(EventA { n }).unwrap();
+    }
+    assert_eq!(Some(&Events::EventA(EventA { n: 0 })), queue.peek());
+    let pending: heapless::Vec<_, 4> = queue.iter().cloned().collect();
+    assert_eq!(
+        &[
+            Events::EventA(EventA { n: 0 }),
+            Events::EventA(EventA { n: 1 }),
+            Events::EventA(EventA { n: 2 })
+        ],
+        pending.as_slice()
+    );
+    assert_eq!(3, queue.len());
+}
+
+#[cfg(all(test, feature = "std"))]
+fn test_queue_management<Q: FsmEventQueue<TestFsm>>(mut queue: Q) {
+    use super::tests_fsm::{EventA, Events};
+
+    for n in 0..6 {
+        queue.enqueue(EventA { n }).unwrap();
+    }
+    queue
+        .retain(|ev| matches!(ev, Events::EventA(EventA { n }) if n % 2 == 0))
+        .unwrap();
+    assert_eq!(3, queue.len());
+
+    let pending: Vec<_> = core::iter::from_fn(|| queue.dequeue()).collect();
+    assert_eq!(
+        vec![
+            Events::EventA(EventA { n: 0 }),
+            Events::EventA(EventA { n: 2 }),
+            Events::EventA(EventA { n: 4 })
+        ],
+        pending
+    );
+
+    for n in 0..3 {
+        queue.enqueue(EventA { n }).unwrap();
+    }
+    queue.clear();
+    assert_eq!(0, queue.len());
+    assert_eq!(None, queue.dequeue());
+}
+
+#[cfg(test)]
+fn test_priority_order<Q: FsmEventQueue<TestFsm>>(mut queue: Q) {
+    use super::tests_fsm::{EventA, Events};
+
+    // the events above 100 have a higher priority
+    for n in [1, 2, 101, 3, 102] {
+        queue.enqueue(EventA { n }).unwrap();
+    }
+
+    let order: heapless::Vec<_, 8> = core::iter::from_fn(|| queue.dequeue())
+        .map(|ev| match ev {
+            Events::EventA(EventA { n }) => n,
+        })
+        .collect();
+    assert_eq!(&[101, 102, 1, 2, 3], order.as_slice());
 }
 
 #[cfg(test)]
@@ -1,4 +1,4 @@
-use crate::{lib::*, FsmBackend};
+use crate::{lib::*, FsmBackend, FsmTransitionTaken};
 
 use crate::FsmResult;
 
@@ -14,9 +14,19 @@ where
         + Copy
         + Debug
         + Default
+        + PartialEq
         + AsRef<[FsmCurrentState<Self::StateKind>]>
         + AsMut<[FsmCurrentState<Self::StateKind>]>
         + 'static;
+    /// An array of the transitions taken by a dispatched event, one for each region.
+    type RegionTransitions: Clone
+        + Copy
+        + Debug
+        + Default
+        + PartialEq
+        + AsRef<[Option<FsmTransitionTaken>]>
+        + AsMut<[Option<FsmTransitionTaken>]>
+        + 'static;
 }
 
 /// The current state of the FSM.
@@ -91,3 +101,18 @@ pub trait FsmStateTransitionAsRef<T1, T2> {
 pub trait FsmStateTransitionAsMut<T1, T2> {
     fn as_state_transition_mut(&mut self) -> (&mut T1, &mut T2);
 }
+
+/// Borrow a state that might not be stored. With the enum storage only the active states are
+/// stored, while `AsRef` and `AsMut` panic for the inactive ones.
+pub trait FsmStatesTryAsRef<S> {
+    /// `None` if the state isn't stored.
+    fn try_as_ref(&self) -> Option<&S>;
+    /// `None` if the state isn't stored.
+    fn try_as_mut(&mut self) -> Option<&mut S>;
+}
+
+/// Stores the state as the active one of its region, dropping the previously active one. Used
+/// when only the active states are stored.
+pub trait FsmStatesReplace<S> {
+    fn replace_state(&mut self, state: S);
+}
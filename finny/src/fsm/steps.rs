@@ -0,0 +1,199 @@
+use arraydeque::{ArrayDeque, Wrapping};
+
+use crate::lib::*;
+use crate::{FsmBackend, FsmError, FsmEvent, FsmResult, FsmStates};
+
+/// The number of the last microsteps that are remembered for the detection of the cycles.
+pub const FSM_STEP_HISTORY: usize = 8;
+/// The number of the last events that are reported when the dispatch is aborted.
+pub const FSM_STEP_LAST_EVENTS: usize = 4;
+
+/// Limits the number of microsteps of a single dispatch, which would otherwise spin forever
+/// if the actions keep enqueueing new events. A microstep is a single event dispatched to the
+/// machine: the dispatched event, each evaluation of the change transitions and each event
+/// from the queue.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FsmStepLimit {
+    /// The maximum number of microsteps. Unbounded if `None`.
+    pub max_steps: Option<usize>,
+    /// Stop once the same sequence of current states and events keeps repeating over the last
+    /// `FSM_STEP_HISTORY` microsteps. The context isn't compared, so only use this if the
+    /// machine's progress is reflected in its states.
+    pub detect_cycles: bool,
+}
+
+impl FsmStepLimit {
+    pub fn new(max_steps: usize) -> Self {
+        FsmStepLimit {
+            max_steps: Some(max_steps),
+            detect_cycles: false,
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cycle_detection(mut self) -> Self {
+        self.detect_cycles = true;
+        self
+    }
+}
+
+/// The dispatch was aborted, it didn't run to completion.
+#[derive(Debug, PartialEq)]
+pub struct FsmStepLimitExceeded {
+    /// The number of the executed microsteps.
+    pub steps: usize,
+    /// The names of the last dispatched events, the most recent one last.
+    pub last_events: heapless::Vec<&'static str, FSM_STEP_LAST_EVENTS>,
+    /// The dispatch was aborted because a cycle was detected, before reaching the limit.
+    pub cycle: bool,
+}
+
+/// Counts the microsteps of a single dispatch.
+pub(crate) struct FsmSteps<F: FsmBackend> {
+    limit: FsmStepLimit,
+    steps: usize,
+    history: ArrayDeque<
+        (
+            <<F as FsmBackend>::States as FsmStates<F>>::CurrentState,
+            &'static str,
+        ),
+        FSM_STEP_HISTORY,
+        Wrapping,
+    >,
+}
+
+impl<F: FsmBackend> FsmSteps<F> {
+    pub fn new(limit: FsmStepLimit) -> Self {
+        FsmSteps {
+            limit,
+            steps: 0,
+            history: ArrayDeque::new(),
+        }
+    }
+
+    /// Account for the event that is about to be dispatched.
+    pub fn step(
+        &mut self,
+        event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
+        current_states: <<F as FsmBackend>::States as FsmStates<F>>::CurrentState,
+    ) -> FsmResult<()> {
+        self.check()?;
+        self.record(event, current_states);
+        Ok(())
+    }
+
+    /// Fails if there is no room left for another microstep.
+    pub fn check(&self) -> FsmResult<()> {
+        let cycle = self.limit.detect_cycles && self.is_cycle();
+        let over_limit = matches!(self.limit.max_steps, Some(max) if self.steps >= max);
+
+        if cycle || over_limit {
+            let skip = self.history.len().saturating_sub(FSM_STEP_LAST_EVENTS);
+            let last_events = self.history.iter().skip(skip).map(|(_, ev)| *ev).collect();
+            return Err(FsmError::StepLimitExceeded(FsmStepLimitExceeded {
+                steps: self.steps,
+                last_events,
+                cycle,
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Account for the event without checking the limit, once `check` has passed.
+    pub fn record(
+        &mut self,
+        event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
+        current_states: <<F as FsmBackend>::States as FsmStates<F>>::CurrentState,
+    ) {
+        let name = match event {
+            FsmEvent::Start => "Fsm::Start",
+            FsmEvent::Stop => "Fsm::Stop",
+            FsmEvent::Change => "Fsm::Change",
+            FsmEvent::Timer(_) => "Fsm::Timer",
+            FsmEvent::Event(ev) => F::event_name(ev),
+        };
+
+        self.steps += 1;
+        self.history.push_back((current_states, name));
+    }
+
+    /// Does the whole history consist of a repeated sequence?
+    fn is_cycle(&self) -> bool {
+        if !self.history.is_full() {
+            return false;
+        }
+
+        (1..=FSM_STEP_HISTORY / 2).any(|period| {
+            self.history
+                .iter()
+                .zip(self.history.iter().skip(period))
+                .all(|(a, b)| a == b)
+        })
+    }
+}
+
+#[cfg(test)]
+use super::tests_fsm::{EventA, TestFsm};
+
+#[test]
+fn test_step_limit() {
+    let mut steps = FsmSteps::<TestFsm>::new(FsmStepLimit::new(2));
+    let states = Default::default();
+    let ev = FsmEvent::Event(EventA { n: 1 }.into());
+
+    assert_eq!(Ok(()), steps.step(&ev, states));
+    assert_eq!(Ok(()), steps.step(&FsmEvent::Change, states));
+
+    match steps.step(&ev, states) {
+        Err(FsmError::StepLimitExceeded(e)) => {
+            assert_eq!(2, e.steps);
+            assert_eq!(&["EventA", "Fsm::Change"], e.last_events.as_slice());
+            assert!(!e.cycle);
+        }
+        _ => panic!("Expected the step limit to be exceeded."),
+    }
+}
+
+#[test]
+fn test_step_cycle() {
+    let mut steps = FsmSteps::<TestFsm>::new(FsmStepLimit::unlimited().with_cycle_detection());
+    let states = Default::default();
+    let ev = FsmEvent::Event(EventA { n: 1 }.into());
+
+    for _ in 0..FSM_STEP_HISTORY / 2 {
+        assert_eq!(Ok(()), steps.step(&ev, states));
+        assert_eq!(Ok(()), steps.step(&FsmEvent::Change, states));
+    }
+
+    match steps.step(&FsmEvent::Change, states) {
+        Err(FsmError::StepLimitExceeded(e)) => {
+            assert_eq!(FSM_STEP_HISTORY, e.steps);
+            assert!(e.cycle);
+        }
+        _ => panic!("Expected a cycle."),
+    }
+}
+
+#[test]
+fn test_step_cycle_of_three() {
+    let mut steps = FsmSteps::<TestFsm>::new(FsmStepLimit::unlimited().with_cycle_detection());
+    let states = Default::default();
+    let ev = FsmEvent::Event(EventA { n: 1 }.into());
+    let cycle = [ev, FsmEvent::Change, FsmEvent::Start];
+
+    for ev in cycle.iter().cycle().take(FSM_STEP_HISTORY) {
+        assert_eq!(Ok(()), steps.step(ev, states));
+    }
+
+    match steps.step(&FsmEvent::Change, states) {
+        Err(FsmError::StepLimitExceeded(e)) => {
+            assert_eq!(FSM_STEP_HISTORY, e.steps);
+            assert!(e.cycle);
+        }
+        _ => panic!("Expected a cycle."),
+    }
+}
@@ -0,0 +1,157 @@
+use crate::lib::*;
+use crate::FsmBackend;
+#[cfg(feature = "alloc")]
+use crate::FsmError;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, collections::VecDeque};
+
+/// The future of an async action or entry handler. It completes with an optional event, which
+/// is dispatched before any other event.
+#[cfg(feature = "alloc")]
+pub type FsmAsyncTask<F> =
+    Pin<Box<dyn Future<Output = Option<<F as FsmBackend>::Events>> + Send + 'static>>;
+
+/// Collects the futures of the async actions, see `action_async` and `on_entry_async`.
+pub trait FsmAsyncTasks<F: FsmBackend> {
+    /// Run the task once the step is done. A task that can't be run fails the dispatch.
+    #[cfg(feature = "alloc")]
+    fn spawn(&mut self, task: FsmAsyncTask<F>);
+}
+
+/// The tasks of the frontends without `alloc`, which have no async actions.
+#[cfg(not(feature = "alloc"))]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FsmAsyncTasksNull;
+
+#[cfg(not(feature = "alloc"))]
+impl<F: FsmBackend> FsmAsyncTasks<F> for FsmAsyncTasksNull {}
+
+/// The futures of the async actions, awaited by the frontend's `dispatch_async` in the order
+/// they were spawned. The tasks are accepted only while they are awaited, the ones spawned by
+/// the sync dispatch are rejected and fail it with `FsmError::NotSupported`.
+#[cfg(feature = "alloc")]
+pub struct FsmAsyncTasksQueue<F: FsmBackend> {
+    tasks: VecDeque<FsmAsyncTask<F>>,
+    awaited: bool,
+    rejected: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<F: FsmBackend> FsmAsyncTasksQueue<F> {
+    pub fn new() -> Self {
+        FsmAsyncTasksQueue {
+            tasks: VecDeque::new(),
+            awaited: false,
+            rejected: 0,
+        }
+    }
+
+    /// Number of the tasks that are waiting to be awaited.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub(crate) fn next(&mut self) -> Option<FsmAsyncTask<F>> {
+        self.tasks.pop_front()
+    }
+
+    /// Accept the tasks, set while the async dispatch awaits them.
+    pub(crate) fn set_awaited(&mut self, awaited: bool) {
+        self.awaited = awaited;
+    }
+
+    /// Drop the tasks that weren't awaited and stop accepting the new ones.
+    pub(crate) fn reset(&mut self) {
+        self.tasks.clear();
+        self.awaited = false;
+        self.rejected = 0;
+    }
+
+    /// Fails if any of the tasks were rejected since the last check.
+    pub(crate) fn check_rejected(&mut self) -> Result<(), FsmError> {
+        match core::mem::take(&mut self.rejected) {
+            0 => Ok(()),
+            _ => Err(FsmError::NotSupported),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F: FsmBackend> Default for FsmAsyncTasksQueue<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F: FsmBackend> FsmAsyncTasks<F> for FsmAsyncTasksQueue<F> {
+    fn spawn(&mut self, task: FsmAsyncTask<F>) {
+        if self.awaited {
+            self.tasks.push_back(task);
+        } else {
+            self.rejected += 1;
+        }
+    }
+}
+
+/// Used to funnel the async actions of the sub-machine up to the parent's tasks.
+pub struct FsmAsyncTasksSub<'a, F, FSub>
+where
+    F: FsmBackend,
+{
+    pub parent: &'a mut dyn FsmAsyncTasks<F>,
+    pub _sub_fsm: PhantomData<FSub>,
+}
+
+impl<'a, F, FSub> FsmAsyncTasks<FSub> for FsmAsyncTasksSub<'a, F, FSub>
+where
+    F: FsmBackend,
+    FSub: FsmBackend,
+    <F as FsmBackend>::Events: From<<FSub as FsmBackend>::Events> + 'static,
+    <FSub as FsmBackend>::Events: 'static,
+{
+    #[cfg(feature = "alloc")]
+    fn spawn(&mut self, task: FsmAsyncTask<FSub>) {
+        self.parent
+            .spawn(Box::pin(async move { task.await.map(Into::into) }))
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+use super::tests_fsm::TestFsm;
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_async_tasks() {
+    use super::tests_fsm::{EventA, Events};
+
+    let mut tasks = FsmAsyncTasksQueue::<TestFsm>::new();
+    tasks.set_awaited(true);
+    tasks.spawn(Box::pin(async { Some(EventA { n: 1 }.into()) }));
+    tasks.spawn(Box::pin(async { None }));
+    assert_eq!(2, tasks.len());
+    assert!(tasks.check_rejected().is_ok());
+
+    // the tasks don't wake anything, so they can be polled with a no-op waker
+    let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+    let mut first = tasks.next().unwrap();
+    assert_eq!(
+        core::task::Poll::Ready(Some(Events::EventA(EventA { n: 1 }))),
+        first.as_mut().poll(&mut cx)
+    );
+    assert_eq!(1, tasks.len());
+
+    // nothing would await the tasks of the sync dispatch
+    tasks.set_awaited(false);
+    tasks.spawn(Box::pin(async { None }));
+    assert_eq!(1, tasks.len());
+    assert_eq!(Err(FsmError::NotSupported), tasks.check_rejected());
+    assert!(tasks.check_rejected().is_ok());
+}
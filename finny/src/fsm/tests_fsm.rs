@@ -1,6 +1,9 @@
 //! A minimal, internal FSM for unit tests, manually written.
 
-use crate::{AllVariants, FsmBackend, FsmCurrentState, FsmStates};
+use crate::{
+    AllVariants, FsmBackend, FsmCurrentState, FsmDelayedEvent, FsmDelayedSlot, FsmEventPriority,
+    FsmStates, FsmTransitionTaken,
+};
 use derive_more::From;
 
 #[derive(Default)]
@@ -27,6 +30,7 @@ pub enum StateKind {
 impl FsmStates<TestFsm> for States {
     type StateKind = StateKind;
     type CurrentState = [FsmCurrentState<StateKind>; 1];
+    type RegionTransitions = [Option<FsmTransitionTaken>; 1];
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, From)]
@@ -36,11 +40,25 @@ pub enum Events {
 
 impl AsRef<str> for Events {
     fn as_ref(&self) -> &'static str {
-        todo!()
+        TestFsm::event_name(self)
+    }
+}
+impl FsmEventPriority for Events {
+    fn priority(&self) -> i32 {
+        match self {
+            Events::EventA(ev) if ev.n > 100 => 1,
+            Events::EventA(_) => 0,
+        }
     }
 }
+
+#[allow(dead_code)]
+pub const TEST_FSM_DELAYED_SLOTS: usize = 4;
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum FsmBackendTimers {}
+pub enum FsmBackendTimers {
+    FsmDelayed(FsmDelayedSlot),
+}
 
 impl AllVariants for FsmBackendTimers {
     type Iter = core::iter::Once<FsmBackendTimers>;
@@ -55,6 +73,24 @@ impl FsmBackend for TestFsm {
     type States = States;
     type Events = Events;
     type Timers = FsmBackendTimers;
+    type Outputs = ();
+    type DelayedSlots = [Option<FsmDelayedEvent<Self>>; TEST_FSM_DELAYED_SLOTS];
+
+    fn event_name(event: &Self::Events) -> &'static str {
+        match event {
+            Events::EventA(_) => "EventA",
+        }
+    }
+
+    fn delayed_slot(timer: &Self::Timers) -> Option<FsmDelayedSlot> {
+        match timer {
+            FsmBackendTimers::FsmDelayed(slot) => Some(*slot),
+        }
+    }
+
+    fn delayed_timer(slot: FsmDelayedSlot) -> Option<Self::Timers> {
+        Some(FsmBackendTimers::FsmDelayed(slot))
+    }
 
     fn dispatch_event<Q, I, T>(
         _ctx: crate::DispatchContext<Self, Q, I, T>,
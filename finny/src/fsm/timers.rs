@@ -1,4 +1,7 @@
-use crate::{lib::*, AllVariants, DispatchContext, FsmError, FsmEvent, FsmEventQueue, Inspect};
+use crate::{
+    lib::*, AllVariants, DispatchContext, FsmEnqueueResult, FsmError, FsmEvent, FsmEventQueue,
+    FsmStatesTryAsRef, Inspect,
+};
 use crate::{FsmBackend, FsmResult};
 
 /// Associate some data with a specific timer ID.
@@ -79,14 +82,17 @@ where
         }
     }
 
+    /// Enqueue the event of the triggered timer. The event that the queue rejected is returned
+    /// along with the error.
     fn execute_trigger<'a, 'b, 'c, 'd, Q, I, T>(
         id: F::Timers,
         context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
         inspect: &mut I,
-    ) where
+    ) -> FsmEnqueueResult<F>
+    where
         Q: FsmEventQueue<F>,
         I: Inspect,
-        <F as FsmBackend>::States: AsRef<S>,
+        <F as FsmBackend>::States: FsmStatesTryAsRef<S>,
         <F as FsmBackend>::States: AsRef<Self>,
         T: FsmTimers<F>,
     {
@@ -94,19 +100,32 @@ where
         let timer: &Self = context.backend.states.as_ref();
         match timer.get_instance() {
             Some(_) => {
-                match Self::trigger(&context.backend.context, context.backend.states.as_ref()) {
+                // the timers that outlive their state can't reach it with the enum storage
+                let state: Option<&S> = context.backend.states.try_as_ref();
+                let state = match state {
+                    Some(state) => state,
+                    None => {
+                        inspect.info("The timer's state isn't stored, the trigger was skipped.");
+                        return Ok(());
+                    }
+                };
+
+                match Self::trigger(&context.backend.context, state) {
                     Some(ev) => {
-                        let inspect =
-                            inspect.new_event::<F>(&FsmEvent::Event(ev.clone()), context.backend);
-                        match context.queue.enqueue(ev) {
-                            Ok(_) => {
-                                inspect.info("The event triggered by the timer was enqueued.");
-                            }
-                            Err(e) => {
-                                inspect.on_error(
-                                    "The event triggered by the timer couldn't be enqueued.",
-                                    &e,
-                                );
+                        let ev = FsmEvent::Event(ev);
+                        let inspect = inspect.new_event::<F>(&ev, context.backend);
+                        if let FsmEvent::Event(ev) = ev {
+                            match context.queue.try_enqueue(ev) {
+                                Ok(_) => {
+                                    inspect.info("The event triggered by the timer was enqueued.");
+                                }
+                                Err((e, ev)) => {
+                                    inspect.on_error(
+                                        "The event triggered by the timer couldn't be enqueued.",
+                                        &e,
+                                    );
+                                    return Err((e, ev));
+                                }
                             }
                         }
                     }
@@ -118,6 +137,8 @@ where
                 inspect.on_error("Timer hasn't been started.", &error);
             }
         }
+
+        Ok(())
     }
 }
 
@@ -167,6 +188,12 @@ where
     /// Return the timer that was triggered. Poll this until it returns None. The events
     /// should be dequeued in a FIFO manner.
     fn get_triggered_timer(&mut self) -> Option<<F as FsmBackend>::Timers>;
+
+    /// The time until the next timer triggers, for the dispatch loops that sleep in between.
+    /// `None` if there are no running timers, or if the implementation can't tell.
+    fn time_to_next_timer(&mut self) -> Option<Duration> {
+        None
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -232,3 +259,29 @@ where
         None
     }
 }
+
+/// Keeps the events of the triggered timers that the queue rejected, so that they are returned
+/// to the caller instead of being dropped.
+pub trait FsmRejectedEvents<F: FsmBackend> {
+    fn reject(&mut self, event: <F as FsmBackend>::Events);
+}
+
+/// Used to funnel the rejected events of the sub-machine up to the parent.
+pub struct FsmRejectedEventsSub<'a, F, FSub>
+where
+    F: FsmBackend,
+{
+    pub parent: &'a mut dyn FsmRejectedEvents<F>,
+    pub _sub_fsm: PhantomData<FSub>,
+}
+
+impl<'a, F, FSub> FsmRejectedEvents<FSub> for FsmRejectedEventsSub<'a, F, FSub>
+where
+    F: FsmBackend,
+    FSub: FsmBackend,
+    <F as FsmBackend>::Events: From<<FSub as FsmBackend>::Events>,
+{
+    fn reject(&mut self, event: <FSub as FsmBackend>::Events) {
+        self.parent.reject(event.into());
+    }
+}
@@ -2,8 +2,9 @@
 
 use crate::{lib::*, FsmBackendImpl, FsmDispatchResult, FsmEventQueueSub, FsmTimers, FsmTimersSub};
 use crate::{
-    DispatchContext, EventContext, FsmBackend, FsmCurrentState, FsmEvent, FsmEventQueue,
-    FsmRegionId, FsmStateTransitionAsMut, FsmStates, Inspect,
+    DispatchContext, EventContext, FsmAsyncTasksSub, FsmBackend, FsmCurrentState,
+    FsmDelayedSenderSub, FsmEvent, FsmEventQueue, FsmOutputsSub, FsmRegionId, FsmResult,
+    FsmStateFactory, FsmStateTransitionAsMut, FsmStates, FsmStatesReplace, Inspect,
 };
 
 use super::inspect::InspectFsmEvent;
@@ -13,13 +14,24 @@ pub trait FsmState<F: FsmBackend>
 where
     Self: Sized,
 {
-    /// Action that is executed whenever this state is being entered.
-    fn on_entry<'a, Q: FsmEventQueue<F>>(&mut self, context: &mut EventContext<'a, F, Q>);
-    /// Action that is executed whenever this state is being exited.
-    fn on_exit<'a, Q: FsmEventQueue<F>>(&mut self, context: &mut EventContext<'a, F, Q>);
+    /// Action that is executed whenever this state is being entered. The event is the one
+    /// that triggered the transition into this state.
+    fn on_entry<'a, Q: FsmEventQueue<F>>(
+        &mut self,
+        context: &mut EventContext<'a, F, Q>,
+        fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
+    );
+    /// Action that is executed whenever this state is being exited. The event is the one
+    /// that triggered the transition out of this state.
+    fn on_exit<'a, Q: FsmEventQueue<F>>(
+        &mut self,
+        context: &mut EventContext<'a, F, Q>,
+        fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
+    );
 
     fn execute_on_entry<'a, 'b, 'c, 'd, Q, I, T>(
         context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
+        fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
         region: FsmRegionId,
     ) where
         Q: FsmEventQueue<F>,
@@ -31,6 +43,10 @@ where
             context: &mut context.backend.context,
             region,
             queue: context.queue,
+            outputs: context.outputs,
+            delayed: context.delayed,
+            tasks: context.tasks,
+            reply: context.reply.as_deref_mut(),
         };
 
         // inspection
@@ -43,11 +59,12 @@ where
         }
 
         let state: &mut Self = context.backend.states.as_mut();
-        state.on_entry(&mut event_context);
+        state.on_entry(&mut event_context, fsm_event);
     }
 
     fn execute_on_exit<'a, 'b, 'c, 'd, Q, I, T>(
         context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
+        fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
         region: FsmRegionId,
     ) where
         Q: FsmEventQueue<F>,
@@ -59,10 +76,14 @@ where
             context: &mut context.backend.context,
             queue: context.queue,
             region,
+            outputs: context.outputs,
+            delayed: context.delayed,
+            tasks: context.tasks,
+            reply: context.reply.as_deref_mut(),
         };
 
         let state: &mut Self = context.backend.states.as_mut();
-        state.on_exit(&mut event_context);
+        state.on_exit(&mut event_context, fsm_event);
 
         // inspection
         {
@@ -101,6 +122,10 @@ pub trait FsmTransitionGuard<F: FsmBackend, E> {
             context: &mut context.backend.context,
             queue: context.queue,
             region,
+            outputs: context.outputs,
+            delayed: context.delayed,
+            tasks: context.tasks,
+            reply: context.reply.as_deref_mut(),
         };
 
         let guard_result = Self::guard(event, &event_context, &context.backend.states);
@@ -113,9 +138,31 @@ pub trait FsmTransitionGuard<F: FsmBackend, E> {
 
 /// The transition that starts the machine, triggered using the `start()` method.
 pub trait FsmTransitionFsmStart<F: FsmBackend, TInitialState> {
+    /// The condition of an initial choice. The machine starts in this transition's state if
+    /// it returns true.
+    fn choice(_context: &<F as FsmBackend>::Context) -> bool {
+        true
+    }
+
+    fn execute_choice<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(
+        context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
+        inspect_event_ctx: &mut I,
+    ) -> bool
+    where
+        I: Inspect,
+        Self: Sized,
+        T: FsmTimers<F>,
+    {
+        let choice_result = Self::choice(&context.backend.context);
+
+        inspect_event_ctx.on_guard::<Self>(choice_result);
+
+        choice_result
+    }
+
     fn execute_transition<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(
         context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
-        _fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
+        fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
         region: FsmRegionId,
         inspect_event_ctx: &mut I,
     ) where
@@ -129,12 +176,37 @@ pub trait FsmTransitionFsmStart<F: FsmBackend, TInitialState> {
         let ctx = inspect_event_ctx.for_transition::<Self>();
         ctx.on_state_enter::<TInitialState>();
 
-        <TInitialState>::execute_on_entry(context, region);
+        <TInitialState>::execute_on_entry(context, fsm_event, region);
 
         let cs = context.backend.current_states.as_mut();
         cs[region] = FsmCurrentState::State(<TInitialState>::fsm_state());
     }
 
+    /// Constructs and stores the initial state before entering it. Used when only the active
+    /// states are stored.
+    fn execute_transition_replace<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(
+        context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
+        fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
+        region: FsmRegionId,
+        inspect_event_ctx: &mut I,
+    ) -> FsmResult<()>
+    where
+        I: Inspect,
+        TInitialState: FsmState<F> + FsmStateFactory<F>,
+        <F as FsmBackend>::States: AsMut<TInitialState>,
+        <F as FsmBackend>::States: AsRef<TInitialState>,
+        <F as FsmBackend>::States: FsmStatesReplace<TInitialState>,
+        Self: Sized,
+        T: FsmTimers<F>,
+    {
+        let state = <TInitialState>::new_state(&context.backend.context)?;
+        context.backend.states.replace_state(state);
+
+        Self::execute_transition(context, fsm_event, region, inspect_event_ctx);
+
+        Ok(())
+    }
+
     /// Executed after the transition on the parent FSM (F) and triggers the first `start()` call if necessary. Subsequent
     /// dispatches are handled using the main dispatch table.
     fn execute_on_sub_entry<'a, 'b, 'c, 'd, Q, I, T>(
@@ -147,10 +219,14 @@ pub trait FsmTransitionFsmStart<F: FsmBackend, TInitialState> {
         Q: FsmEventQueue<F>,
         I: Inspect,
         <F as FsmBackend>::Events: From<<TInitialState as FsmBackend>::Events>,
+        <TInitialState as FsmBackend>::Events: TryFrom<<F as FsmBackend>::Events>,
         <F as FsmBackend>::States: AsMut<TInitialState>,
         TInitialState: DerefMut<Target = FsmBackendImpl<TInitialState>>,
         T: FsmTimers<F>,
         <F as FsmBackend>::Timers: From<<TInitialState as FsmBackend>::Timers>,
+        <F as FsmBackend>::Outputs: From<<TInitialState as FsmBackend>::Outputs>,
+        <F as FsmBackend>::Events: 'static,
+        <TInitialState as FsmBackend>::Events: 'static,
     {
         let sub_backend: &mut TInitialState = context.backend.states.as_mut();
         let states = sub_backend.get_current_states();
@@ -167,6 +243,21 @@ pub trait FsmTransitionFsmStart<F: FsmBackend, TInitialState> {
                 _sub_fsm: core::marker::PhantomData::<TInitialState>,
             };
 
+            let mut outputs_adapter = FsmOutputsSub {
+                parent: context.outputs,
+                _sub_fsm: PhantomData::<TInitialState>,
+            };
+
+            let mut delayed_adapter = FsmDelayedSenderSub {
+                parent: context.delayed,
+                _sub_fsm: PhantomData::<TInitialState>,
+            };
+
+            let mut tasks_adapter = FsmAsyncTasksSub {
+                parent: context.tasks,
+                _sub_fsm: PhantomData::<TInitialState>,
+            };
+
             let mut inspect = inspect_event_ctx.for_sub_machine::<TInitialState>();
 
             let sub_dispatch_context = DispatchContext {
@@ -174,6 +265,12 @@ pub trait FsmTransitionFsmStart<F: FsmBackend, TInitialState> {
                 inspect: &mut inspect,
                 queue: &mut queue_adapter,
                 timers: &mut timers_adapter,
+                outputs: &mut outputs_adapter,
+                delayed: &mut delayed_adapter,
+                tasks: &mut tasks_adapter,
+                reply: context.reply.as_deref_mut(),
+                transitions: None,
+                rejected: None,
             };
 
             return TInitialState::dispatch_event(sub_dispatch_context, FsmEvent::Start);
@@ -185,17 +282,37 @@ pub trait FsmTransitionFsmStart<F: FsmBackend, TInitialState> {
 
 /// A transition's action that operates on both the exit and entry states.
 pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
-    /// This action is executed after the first state's exit event, and just before the second event's entry action. It can mutate both states.
+    /// This action is executed after the first state's exit event, and just before the second event's entry action. It can mutate both states,
+    /// and optionally borrow the rest of them.
     fn action<'a, Q: FsmEventQueue<F>>(
         event: &E,
         context: &mut EventContext<'a, F, Q>,
-        from: &mut TStateFrom,
-        to: &mut TStateTo,
+        states: &mut <F as FsmBackend>::States,
     );
 
+    /// The action, used when only the active states are stored. The target state isn't stored
+    /// yet, so both states are passed directly.
+    fn action_with_target<'a, Q: FsmEventQueue<F>>(
+        _event: &E,
+        _context: &mut EventContext<'a, F, Q>,
+        _from: &mut TStateFrom,
+        _to: &mut TStateTo,
+    ) {
+    }
+
+    /// Builds a new instance of the target state from the event. It replaces the stored state
+    /// after the source state's exit action, before the transition's action.
+    fn new_target_state<'a, Q: FsmEventQueue<F>>(
+        _event: &E,
+        _context: &EventContext<'a, F, Q>,
+    ) -> Option<TStateTo> {
+        None
+    }
+
     fn execute_transition<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(
         context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
         event: &E,
+        fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
         region: FsmRegionId,
         inspect_event_ctx: &mut I,
     ) where
@@ -210,7 +327,7 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
     {
         let inspect_ctx = inspect_event_ctx.for_transition::<Self>();
 
-        <TStateFrom>::execute_on_exit(context, region);
+        <TStateFrom>::execute_on_exit(context, fsm_event, region);
 
         // transition action
         {
@@ -220,13 +337,100 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
                 context: &mut context.backend.context,
                 queue: context.queue,
                 region,
+                outputs: context.outputs,
+                delayed: context.delayed,
+                tasks: context.tasks,
+                reply: context.reply.as_deref_mut(),
             };
-            let states: (&mut TStateFrom, &mut TStateTo) =
-                context.backend.states.as_state_transition_mut();
-            Self::action(event, &mut event_context, states.0, states.1);
+
+            if let Some(state) = Self::new_target_state(event, &event_context) {
+                let state_to: &mut TStateTo = context.backend.states.as_mut();
+                *state_to = state;
+            }
+
+            Self::action(event, &mut event_context, &mut context.backend.states);
         }
 
-        <TStateTo>::execute_on_entry(context, region);
+        <TStateTo>::execute_on_entry(context, fsm_event, region);
+
+        let cs = context.backend.current_states.as_mut();
+        cs[region] = FsmCurrentState::State(<TStateTo>::fsm_state());
+    }
+
+    /// Builds the target state from the event, or from the context if the transition doesn't
+    /// have a constructor.
+    fn construct_target_state<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(
+        context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
+        event: &E,
+        region: FsmRegionId,
+    ) -> FsmResult<TStateTo>
+    where
+        I: Inspect,
+        TStateTo: FsmStateFactory<F>,
+        T: FsmTimers<F>,
+    {
+        let state_to = {
+            let event_context = EventContext {
+                context: &mut context.backend.context,
+                queue: context.queue,
+                region,
+                outputs: context.outputs,
+                delayed: context.delayed,
+                tasks: context.tasks,
+                reply: context.reply.as_deref_mut(),
+            };
+            Self::new_target_state(event, &event_context)
+        };
+
+        match state_to {
+            Some(state) => Ok(state),
+            None => <TStateTo>::new_state(&context.backend.context),
+        }
+    }
+
+    /// Used when only the active states are stored. The constructed target state replaces the
+    /// source state after the transition's action.
+    fn execute_transition_replace<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(
+        context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
+        event: &E,
+        fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
+        region: FsmRegionId,
+        inspect_event_ctx: &mut I,
+        mut state_to: TStateTo,
+    ) where
+        I: Inspect,
+        <F as FsmBackend>::States: AsMut<TStateFrom>,
+        <F as FsmBackend>::States: AsMut<TStateTo>,
+        <F as FsmBackend>::States: FsmStatesReplace<TStateTo>,
+        TStateFrom: FsmState<F>,
+        TStateTo: FsmState<F>,
+        Self: Sized,
+        T: FsmTimers<F>,
+    {
+        let inspect_ctx = inspect_event_ctx.for_transition::<Self>();
+
+        <TStateFrom>::execute_on_exit(context, fsm_event, region);
+
+        // transition action
+        {
+            inspect_ctx.on_action::<Self>();
+
+            let mut event_context = EventContext {
+                context: &mut context.backend.context,
+                queue: context.queue,
+                region,
+                outputs: context.outputs,
+                delayed: context.delayed,
+                tasks: context.tasks,
+                reply: context.reply.as_deref_mut(),
+            };
+            let from: &mut TStateFrom = context.backend.states.as_mut();
+            Self::action_with_target(event, &mut event_context, from, &mut state_to);
+        }
+
+        context.backend.states.replace_state(state_to);
+
+        <TStateTo>::execute_on_entry(context, fsm_event, region);
 
         let cs = context.backend.current_states.as_mut();
         cs[region] = FsmCurrentState::State(<TStateTo>::fsm_state());
@@ -244,10 +448,14 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
         Q: FsmEventQueue<F>,
         I: Inspect,
         <F as FsmBackend>::Events: From<<TStateTo as FsmBackend>::Events>,
+        <TStateTo as FsmBackend>::Events: TryFrom<<F as FsmBackend>::Events>,
         <F as FsmBackend>::States: AsMut<TStateTo>,
         TStateTo: DerefMut<Target = FsmBackendImpl<TStateTo>>,
         T: FsmTimers<F>,
         <F as FsmBackend>::Timers: From<<TStateTo as FsmBackend>::Timers>,
+        <F as FsmBackend>::Outputs: From<<TStateTo as FsmBackend>::Outputs>,
+        <F as FsmBackend>::Events: 'static,
+        <TStateTo as FsmBackend>::Events: 'static,
     {
         let sub_backend: &mut TStateTo = context.backend.states.as_mut();
         let states = sub_backend.get_current_states();
@@ -264,6 +472,21 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
                 _sub_fsm: core::marker::PhantomData::<TStateTo>,
             };
 
+            let mut outputs_adapter = FsmOutputsSub {
+                parent: context.outputs,
+                _sub_fsm: PhantomData::<TStateTo>,
+            };
+
+            let mut delayed_adapter = FsmDelayedSenderSub {
+                parent: context.delayed,
+                _sub_fsm: PhantomData::<TStateTo>,
+            };
+
+            let mut tasks_adapter = FsmAsyncTasksSub {
+                parent: context.tasks,
+                _sub_fsm: PhantomData::<TStateTo>,
+            };
+
             let mut inspect = inspect_event_ctx.for_sub_machine::<TStateTo>();
 
             let sub_dispatch_context = DispatchContext {
@@ -271,6 +494,12 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
                 inspect: &mut inspect,
                 queue: &mut queue_adapter,
                 timers: &mut timers_adapter,
+                outputs: &mut outputs_adapter,
+                delayed: &mut delayed_adapter,
+                tasks: &mut tasks_adapter,
+                reply: context.reply.as_deref_mut(),
+                transitions: None,
+                rejected: None,
             };
 
             return TStateTo::dispatch_event(sub_dispatch_context, FsmEvent::Start);
@@ -280,13 +509,43 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
     }
 }
 
+/// The constructor of a transition's target state, see `transition_to_with`.
+pub trait FsmTransitionConstructor<F: FsmBackend, E, TStateTo> {
+    /// Builds a new instance of the target state from the event.
+    fn construct<'a, Q: FsmEventQueue<F>>(event: &E, context: &EventContext<'a, F, Q>) -> TStateTo;
+
+    /// Used when only the active states are stored. Unlike
+    /// `FsmTransitionAction::construct_target_state`, the target state doesn't need a factory.
+    fn construct_target_state_with<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(
+        context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
+        event: &E,
+        region: FsmRegionId,
+    ) -> TStateTo
+    where
+        I: Inspect,
+        T: FsmTimers<F>,
+    {
+        let event_context = EventContext {
+            context: &mut context.backend.context,
+            queue: context.queue,
+            region,
+            outputs: context.outputs,
+            delayed: context.delayed,
+            tasks: context.tasks,
+            reply: context.reply.as_deref_mut(),
+        };
+        Self::construct(event, &event_context)
+    }
+}
+
 /// An internal or self action can only mutate itself.
 pub trait FsmAction<F: FsmBackend, E, State> {
-    /// This action is executed as part of an internal or self transition.
+    /// This action is executed as part of an internal or self transition. It can mutate its state,
+    /// and optionally borrow the rest of them.
     fn action<'a, Q: FsmEventQueue<F>>(
         event: &E,
         context: &mut EventContext<'a, F, Q>,
-        state: &mut State,
+        states: &mut <F as FsmBackend>::States,
     );
     /// Is this a self transition which should trigger the state's exit and entry actions?
     fn should_trigger_state_actions() -> bool;
@@ -304,16 +563,19 @@ pub trait FsmAction<F: FsmBackend, E, State> {
             context: &mut context.backend.context,
             queue: context.queue,
             region,
+            outputs: context.outputs,
+            delayed: context.delayed,
+            tasks: context.tasks,
+            reply: context.reply.as_deref_mut(),
         };
 
-        let state: &mut State = context.backend.states.as_mut();
-
-        Self::action(event, &mut event_context, state);
+        Self::action(event, &mut event_context, &mut context.backend.states);
     }
 
     fn execute_transition<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(
         context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>,
         event: &E,
+        fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
         region: FsmRegionId,
         inspect_event_ctx: &mut I,
     ) where
@@ -326,13 +588,13 @@ pub trait FsmAction<F: FsmBackend, E, State> {
         let _ctx = inspect_event_ctx.for_transition::<Self>();
 
         if Self::should_trigger_state_actions() {
-            <State>::execute_on_exit(context, region);
+            <State>::execute_on_exit(context, fsm_event, region);
         }
 
         Self::execute_action(context, event, region);
 
         if Self::should_trigger_state_actions() {
-            <State>::execute_on_entry(context, region);
+            <State>::execute_on_entry(context, fsm_event, region);
         }
     }
 }
@@ -10,12 +10,17 @@
 //! * Declarative, builder API with a procedural function macro that generate the dispatcher
 //! * Compile-time transition graph validation
 //! * No run-time allocations required, `no_std` support
-//! * Support for generics within the shared context
+//! * Support for generics within the shared context, states and events, and for contexts that borrow
 //! * Transition guards and actions
 //! * State regions, also known as orthogonal states
-//! * Event queueing and run-to-completition execution
+//! * Event queueing and run-to-completition execution, with optional event priorities
 //! * Submachines, also known as Hierarchical State Machines
-//! * Timers on states
+//! * Timers on states, and events delayed by the actions
+//! * Change events, transitions triggered by a condition on the context
+//! * Optional storage of only the active state of each region
+//! * A thread that runs the machine and sleeps until its next timer, fed through a mailbox
+//! * Optional async driver and timers for Tokio, with the `tokio` feature
+//! * Async actions and entry handlers, awaited by `dispatch_async` without breaking run-to-completion
 //!
 //! ## Example
 //!
@@ -80,6 +85,10 @@
 pub mod decl;
 mod fsm;
 
+#[cfg(feature = "std")]
+pub mod actor;
+#[cfg(feature = "tokio")]
+pub mod driver;
 pub mod inspect;
 pub mod timers;
 
@@ -115,7 +124,7 @@ mod lib {
         pub use std::*;
     }
 
-    pub use self::core::any::type_name;
+    pub use self::core::any::{type_name, Any};
     pub use self::core::fmt;
     pub use self::core::fmt::Debug;
     pub use self::core::marker::PhantomData;
@@ -123,6 +132,9 @@ mod lib {
     pub use self::core::result::Result;
     pub use self::core::time::Duration;
 
+    #[cfg(feature = "alloc")]
+    pub use self::core::{future::Future, pin::Pin};
+
     #[cfg(feature = "std")]
     pub use std::collections::VecDeque;
 }
@@ -5,3 +5,6 @@ pub mod std_noalloc;
 pub mod std;
 
 pub mod core;
+
+#[cfg(feature = "tokio")]
+pub mod tokio;
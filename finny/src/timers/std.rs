@@ -23,6 +23,24 @@ enum StdTimer {
     },
 }
 
+impl StdTimer {
+    /// The time until the timer triggers.
+    fn remaining(&self, now: Instant) -> Duration {
+        let (started_at, timeout) = match self {
+            StdTimer::Timeout {
+                started_at,
+                duration,
+            } => (started_at, duration),
+            StdTimer::Interval {
+                started_at,
+                interval,
+            } => (started_at, interval),
+        };
+
+        (*started_at + *timeout).saturating_duration_since(now)
+    }
+}
+
 impl<F> TimersStd<F>
 where
     F: FsmBackend,
@@ -117,4 +135,16 @@ where
 
         None
     }
+
+    fn time_to_next_timer(&mut self) -> Option<Duration> {
+        if self.pending_intervals.is_some() {
+            return Some(Duration::ZERO);
+        }
+
+        let now = Instant::now();
+        self.timers
+            .iter()
+            .map(|(_, timer)| timer.remaining(now))
+            .min()
+    }
 }
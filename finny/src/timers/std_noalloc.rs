@@ -24,6 +24,24 @@ pub enum StdTimer {
     },
 }
 
+impl StdTimer {
+    /// The time until the timer triggers.
+    fn remaining(&self, now: Instant) -> Duration {
+        let (started_at, timeout) = match self {
+            StdTimer::Timeout {
+                started_at,
+                duration,
+            } => (started_at, duration),
+            StdTimer::Interval {
+                started_at,
+                interval,
+            } => (started_at, interval),
+        };
+
+        (*started_at + *timeout).saturating_duration_since(now)
+    }
+}
+
 impl<F, S> TimersStdNoAlloc<F, S>
 where
     F: FsmBackend,
@@ -120,4 +138,20 @@ where
 
         None
     }
+
+    fn time_to_next_timer(&mut self) -> Option<Duration> {
+        if self.pending_intervals.is_some() {
+            return Some(Duration::ZERO);
+        }
+
+        let now = Instant::now();
+        <F as FsmBackend>::Timers::iter()
+            .filter_map(|id| {
+                self.timers
+                    .get_timer_storage_mut(&id)
+                    .as_ref()
+                    .map(|timer| timer.remaining(now))
+            })
+            .min()
+    }
 }
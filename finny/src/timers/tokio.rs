@@ -0,0 +1,151 @@
+//! Timers on top of the Tokio runtime. Each timer is a task that sleeps until it triggers, so
+//! nothing has to be polled. Must be used within a Tokio runtime.
+
+use crate::{FsmBackend, FsmResult, FsmTimers, TimerSettings};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::AbortHandle;
+use tokio::time::{self, Instant};
+
+pub struct TimersTokio<F>
+where
+    F: FsmBackend,
+{
+    timers: Vec<TokioTimer<F>>,
+    /// Tells the triggers of a timer apart from the ones of its canceled predecessors.
+    generation: u64,
+    sender: UnboundedSender<(<F as FsmBackend>::Timers, u64)>,
+    triggered: UnboundedReceiver<(<F as FsmBackend>::Timers, u64)>,
+    pending: Option<(<F as FsmBackend>::Timers, u64)>,
+}
+
+struct TokioTimer<F>
+where
+    F: FsmBackend,
+{
+    id: <F as FsmBackend>::Timers,
+    generation: u64,
+    renew: bool,
+    task: AbortHandle,
+}
+
+impl<F> TimersTokio<F>
+where
+    F: FsmBackend,
+{
+    pub fn new() -> Self {
+        let (sender, triggered) = mpsc::unbounded_channel();
+        Self {
+            timers: vec![],
+            generation: 0,
+            sender,
+            triggered,
+            pending: None,
+        }
+    }
+
+    /// Wait until a timer triggers. Never completes if there are no running timers.
+    pub async fn wait(&mut self) {
+        if self.pending.is_none() {
+            self.pending = self.triggered.recv().await;
+        }
+    }
+}
+
+impl<F> Default for TimersTokio<F>
+where
+    F: FsmBackend,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> Drop for TimersTokio<F>
+where
+    F: FsmBackend,
+{
+    fn drop(&mut self) {
+        for timer in &self.timers {
+            timer.task.abort();
+        }
+    }
+}
+
+impl<F> FsmTimers<F> for TimersTokio<F>
+where
+    F: FsmBackend,
+    <F as FsmBackend>::Timers: Send + 'static,
+{
+    fn create(&mut self, id: <F as FsmBackend>::Timers, settings: &TimerSettings) -> FsmResult<()> {
+        // try to cancel any existing ones
+        self.cancel(id.clone())?;
+
+        self.generation += 1;
+        let generation = self.generation;
+        let sender = self.sender.clone();
+        let timeout = settings.timeout;
+        let renew = settings.renew;
+
+        let trigger = id.clone();
+        let task = tokio::spawn(async move {
+            if renew {
+                let mut interval = time::interval_at(Instant::now() + timeout, timeout);
+                loop {
+                    interval.tick().await;
+                    if sender.send((trigger.clone(), generation)).is_err() {
+                        break;
+                    }
+                }
+            } else {
+                time::sleep(timeout).await;
+                let _ = sender.send((trigger, generation));
+            }
+        });
+
+        self.timers.push(TokioTimer {
+            id,
+            generation,
+            renew,
+            task: task.abort_handle(),
+        });
+
+        Ok(())
+    }
+
+    fn cancel(&mut self, id: <F as FsmBackend>::Timers) -> FsmResult<()> {
+        self.timers.retain(|timer| {
+            if timer.id == id {
+                timer.task.abort();
+                false
+            } else {
+                true
+            }
+        });
+        Ok(())
+    }
+
+    fn get_triggered_timer(&mut self) -> Option<<F as FsmBackend>::Timers> {
+        loop {
+            let (id, generation) = match self.pending.take() {
+                Some(triggered) => triggered,
+                None => self.triggered.try_recv().ok()?,
+            };
+
+            // skip the triggers of the canceled timers
+            let idx = match self
+                .timers
+                .iter()
+                .position(|timer| timer.generation == generation)
+            {
+                Some(idx) => idx,
+                None => continue,
+            };
+
+            if !self.timers[idx].renew {
+                self.timers.remove(idx);
+            }
+
+            return Some(id);
+        }
+    }
+}
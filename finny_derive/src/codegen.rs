@@ -3,14 +3,17 @@ use std::collections::HashSet;
 use crate::{
     codegen_meta::generate_fsm_meta,
     fsm::FsmTypes,
-    parse::{FsmState, FsmStateAction, FsmStateKind},
-    utils::{get_ty_ident, remap_closure_inputs, to_field_name, tokens_to_string},
+    parse::{EventGuardAction, FsmState, FsmStateAction, FsmStateKind},
+    utils::{get_ty_ident, remap_closure_inputs, to_field_name, to_variant_ty, tokens_to_string},
 };
 use proc_macro2::TokenStream;
-use quote::{quote, TokenStreamExt};
+use quote::{format_ident, quote, TokenStreamExt};
+use syn::{parse_quote, spanned::Spanned};
 
 use crate::{
-    parse::{FsmFnInput, FsmStateTransition, FsmTransitionState, FsmTransitionType},
+    parse::{
+        FsmFnInput, FsmStateTransition, FsmTransitionEvent, FsmTransitionState, FsmTransitionType,
+    },
     utils::ty_append,
 };
 
@@ -33,20 +36,34 @@ pub fn generate_fsm_code(
 
     let region_count = fsm.fsm.regions.len();
 
+    let outputs_ty = match fsm.fsm.outputs_ty {
+        Some(ref ty) => quote! { #ty },
+        None => quote! { () },
+    };
+
+    let delayed_slots = fsm.fsm.delayed_slots.as_ref();
+
     let (fsm_generics_impl, fsm_generics_type, fsm_generics_where) =
         fsm.base.fsm_generics.split_for_impl();
 
+    let states_storage_enum = fsm.fsm.codegen_options.states_storage_enum;
+
     let states_store = {
         let mut code_fields = TokenStream::new();
         let mut new_state_fields = TokenStream::new();
         let mut state_variants = TokenStream::new();
         let mut state_accessors = TokenStream::new();
 
+        // per region storage, only for the enum storage
+        let mut region_variants: Vec<_> =
+            fsm.fsm.regions.iter().map(|_| TokenStream::new()).collect();
+        let mut region_sizes: Vec<_> = fsm.fsm.regions.iter().map(|_| quote! { 0 }).collect();
+
         for (_i, (_, state)) in fsm.fsm.states.iter().enumerate() {
             let name = &state.state_storage_field;
             let state_ty = FsmTypes::new(&state.ty, &fsm.base.fsm_generics);
             let ty = state_ty.get_fsm_ty();
-            let ty_name = state_ty.get_fsm_no_generics_ty();
+            let ty_name = &state.get_variant_ty();
             let _field_name_str = get_ty_ident(ty);
 
             for timer in &state.timers {
@@ -73,15 +90,84 @@ pub fn generate_fsm_code(
                 });
             }
 
-            code_fields.append_all(quote! {
-                /// state storage struct field
-                #name: #ty,
-            });
             state_variants.append_all(quote! {
                 /// state variant
                 #ty_name,
             });
 
+            let region_id = fsm
+                .fsm
+                .regions
+                .iter()
+                .position(|r| r.states.iter().any(|s| s.ty == state.ty))
+                .unwrap_or_default();
+            let region_size = &mut region_sizes[region_id];
+            *region_size = quote! { #region_size + core::mem::size_of::<#ty>() };
+
+            if states_storage_enum {
+                let region_field = format_ident!("region_{}", region_id);
+                let region_ty = ty_append(&states_store_ty, &format!("Region{}", region_id));
+
+                region_variants[region_id].append_all(quote! {
+                    /// state variant
+                    #ty_name(#ty),
+                });
+
+                state_accessors.append_all(quote! {
+                    impl #fsm_generics_impl finny::FsmStatesTryAsRef<#ty> for #states_store_ty #fsm_generics_type #fsm_generics_where {
+                        /// Convert to a shared reference, if the state is active.
+                        fn try_as_ref(&self) -> Option<& #ty> {
+                            match self. #region_field {
+                                #region_ty :: #ty_name (ref state) => Some(state),
+                                _ => None
+                            }
+                        }
+
+                        /// Convert to a mutable reference, if the state is active.
+                        fn try_as_mut(&mut self) -> Option<&mut #ty> {
+                            match self. #region_field {
+                                #region_ty :: #ty_name (ref mut state) => Some(state),
+                                _ => None
+                            }
+                        }
+                    }
+
+                    impl #fsm_generics_impl core::convert::AsRef<#ty> for #states_store_ty #fsm_generics_type #fsm_generics_where {
+                        /// Convert to a shared reference. Panics if the state isn't active.
+                        fn as_ref(&self) -> & #ty {
+                            match self. #region_field {
+                                #region_ty :: #ty_name (ref state) => state,
+                                _ => panic!("The state {} isn't active.", stringify!(#ty_name))
+                            }
+                        }
+                    }
+
+                    impl #fsm_generics_impl core::convert::AsMut<#ty> for #states_store_ty #fsm_generics_type #fsm_generics_where {
+                        /// Convert to a mutable reference. Panics if the state isn't active.
+                        fn as_mut(&mut self) -> &mut #ty {
+                            match self. #region_field {
+                                #region_ty :: #ty_name (ref mut state) => state,
+                                _ => panic!("The state {} isn't active.", stringify!(#ty_name))
+                            }
+                        }
+                    }
+
+                    impl #fsm_generics_impl finny::FsmStatesReplace<#ty> for #states_store_ty #fsm_generics_type #fsm_generics_where {
+                        /// Store as the active state of the region.
+                        fn replace_state(&mut self, state: #ty) {
+                            self. #region_field = #region_ty :: #ty_name (state);
+                        }
+                    }
+                });
+
+                continue;
+            }
+
+            code_fields.append_all(quote! {
+                /// state storage struct field
+                #name: #ty,
+            });
+
             let new_state_field = match state.kind {
                 FsmStateKind::Normal => {
                     quote! {
@@ -124,6 +210,18 @@ pub fn generate_fsm_code(
             new_state_fields.append_all(new_state_field);
 
             state_accessors.append_all(quote! {
+                impl #fsm_generics_impl finny::FsmStatesTryAsRef<#ty> for #states_store_ty #fsm_generics_type #fsm_generics_where {
+                    /// Convert to a shared reference, all of the states are stored.
+                    fn try_as_ref(&self) -> Option<& #ty> {
+                        Some(&self. #name)
+                    }
+
+                    /// Convert to a mutable reference, all of the states are stored.
+                    fn try_as_mut(&mut self) -> Option<&mut #ty> {
+                        Some(&mut self. #name)
+                    }
+                }
+
                 impl #fsm_generics_impl core::convert::AsRef<#ty> for #states_store_ty #fsm_generics_type #fsm_generics_where {
                     /// Convert to a shared reference.
                     fn as_ref(&self) -> & #ty {
@@ -140,10 +238,39 @@ pub fn generate_fsm_code(
             });
         }
 
+        let mut regions_storage = TokenStream::new();
+        if states_storage_enum {
+            for (region_id, variants) in region_variants.iter().enumerate() {
+                let region_field = format_ident!("region_{}", region_id);
+                let region_ty = ty_append(&states_store_ty, &format!("Region{}", region_id));
+
+                code_fields.append_all(quote! {
+                    /// the active state of the region
+                    #region_field: #region_ty #fsm_generics_type,
+                });
+                new_state_fields.append_all(quote! {
+                    #region_field: #region_ty::Stopped,
+                });
+                region_sizes[region_id] =
+                    quote! { core::mem::size_of::<#region_ty #fsm_generics_type>() };
+
+                regions_storage.append_all(quote! {
+                    /// Storage of the active state of a region.
+                    pub enum #region_ty #fsm_generics_type #fsm_generics_where {
+                        /// No state is active.
+                        Stopped,
+                        #variants
+                        #[doc(hidden)]
+                        _Fsm(core::convert::Infallible, core::marker::PhantomData< #fsm_ty #fsm_generics_type >)
+                    }
+                });
+            }
+        }
+
         let mut transition_states = TokenStream::new();
 
         let mut transitions_seen = HashSet::new();
-        for region in &fsm.fsm.regions {
+        for region in fsm.fsm.regions.iter().filter(|_| !states_storage_enum) {
             for transition in &region.transitions {
                 match transition.ty {
                     FsmTransitionType::StateTransition(ref s) => {
@@ -196,6 +323,15 @@ pub fn generate_fsm_code(
                 }
             }
 
+            impl #fsm_generics_impl #states_store_ty #fsm_generics_type #fsm_generics_where {
+                /// The size of the states storage, in bytes.
+                pub const SIZE: usize = core::mem::size_of::<Self>();
+                /// The size of the states of each region, in bytes.
+                pub const REGION_SIZES: [usize; #region_count] = [ #(#region_sizes),* ];
+            }
+
+            #regions_storage
+
             /// States
             #[derive(Copy, Clone, Debug, PartialEq)]
             pub enum #states_enum_ty {
@@ -205,6 +341,7 @@ pub fn generate_fsm_code(
             impl #fsm_generics_impl finny::FsmStates< #fsm_ty #fsm_generics_type > for #states_store_ty #fsm_generics_type #fsm_generics_where {
                 type StateKind = #states_enum_ty;
                 type CurrentState = [finny::FsmCurrentState<Self::StateKind>; #region_count];
+                type RegionTransitions = [Option<finny::FsmTransitionTaken>; #region_count];
             }
 
             #state_accessors
@@ -226,16 +363,30 @@ pub fn generate_fsm_code(
 
         let mut variants = TokenStream::new();
         let mut as_ref_str = TokenStream::new();
+        let mut priorities = TokenStream::new();
+        let mut sub_conversions = TokenStream::new();
+        let mut clone_bounds = TokenStream::new();
+        let mut clone_arms = TokenStream::new();
         let mut i = 0;
 
-        for (ty, _ev) in fsm.fsm.events.iter() {
-            let ty_str = crate::utils::tokens_to_string(ty);
+        for (ty, ev) in fsm.fsm.events.iter() {
+            let ty_str = crate::utils::tokens_to_string(ty).replace(' ', "");
+            let variant = to_variant_ty(ty);
 
             variants.append_all(quote! {
                 /// event variant
-                #ty ( #ty ),
+                #variant ( #ty ),
             });
-            as_ref_str.append_all(quote! { #event_enum_ty:: #ty(_) => #ty_str, });
+            as_ref_str.append_all(quote! { #event_enum_ty:: #variant(_) => #ty_str, });
+            let priority = match ev.priority {
+                Some(ref priority) => quote! { #priority },
+                None => quote! { 0 },
+            };
+            priorities.append_all(quote! { #event_enum_ty:: #variant(_) => #priority, });
+            clone_bounds.append_all(quote! { for<'fsm_clone> #ty: Clone, });
+            clone_arms.append_all(
+                quote! { #event_enum_ty:: #variant(ev) => #event_enum_ty:: #variant(ev.clone()), },
+            );
             i += 1;
         }
 
@@ -252,6 +403,26 @@ pub fn generate_fsm_code(
             as_ref_str.append_all(quote! {
                 #event_enum_ty :: #sub_fsm_ty(_) => #sub_fsm_event_ty_str ,
             });
+            priorities.append_all(quote! {
+                #event_enum_ty :: #sub_fsm_ty(ev) => finny::FsmEventPriority::priority(ev),
+            });
+            clone_bounds.append_all(quote! { for<'fsm_clone> #sub_fsm_event_ty: Clone, });
+            clone_arms.append_all(quote! {
+                #event_enum_ty :: #sub_fsm_ty(ev) => #event_enum_ty :: #sub_fsm_ty(ev.clone()),
+            });
+            sub_conversions.append_all(quote! {
+                impl ::core::convert::TryFrom<#event_enum_ty> for #sub_fsm_event_ty {
+                    type Error = #event_enum_ty;
+
+                    #[allow(unreachable_patterns)]
+                    fn try_from(ev: #event_enum_ty) -> ::core::result::Result<Self, Self::Error> {
+                        match ev {
+                            #event_enum_ty :: #sub_fsm_ty(ev) => Ok(ev),
+                            ev => Err(ev),
+                        }
+                    }
+                }
+            });
             i += 1;
         }
 
@@ -277,21 +448,61 @@ pub fn generate_fsm_code(
             }
         };
 
+        let clone = match i {
+            0 => quote! { match *self {} },
+            _ => quote! {
+                match self {
+                    #clone_arms
+                }
+            },
+        };
+
+        let priority = match i {
+            0 => quote! { 0 },
+            _ => quote! {
+                match self {
+                    #priorities
+                }
+            },
+        };
+
         let evs = quote! {
             /// Events definitions
             #[derive(finny::bundled::derive_more::From)]
-            #[derive(Clone)]
             #derives
             pub enum #event_enum_ty {
                 #variants
             }
 
+            // cloneable only if all of the events are, the higher-ranked bounds keep the bounds
+            // of the types that aren't `Clone` from failing the build
+            impl Clone for #event_enum_ty where #clone_bounds {
+                fn clone(&self) -> Self {
+                    #clone
+                }
+            }
+
+            impl #event_enum_ty {
+                /// The name of the event.
+                pub fn event_name(&self) -> &'static str {
+                    #as_ref_str
+                }
+            }
+
             impl ::core::convert::AsRef<str> for #event_enum_ty {
                 /// Convert to a shared reference.
                 fn as_ref(&self) -> &'static str {
-                    #as_ref_str
+                    self.event_name()
+                }
+            }
+
+            impl finny::FsmEventPriority for #event_enum_ty {
+                fn priority(&self) -> i32 {
+                    #priority
                 }
             }
+
+            #sub_conversions
         };
 
         evs
@@ -361,17 +572,35 @@ pub fn generate_fsm_code(
                         }
 
                         let action_body = if let Some(ref action) = s.action.action {
-                            let remap = remap_closure_inputs(
-                                &action.inputs,
-                                vec![quote! { event }, quote! { context }, quote! { state }]
-                                    .as_slice(),
-                            )?;
+                            // the sibling states are optional
+                            let with_siblings = action.inputs.len() == 4;
+                            if with_siblings && states_storage_enum {
+                                return Err(syn::Error::new(
+                                    action.span(),
+                                    "Sibling states aren't supported with the enum states storage.",
+                                ));
+                            }
+                            let mut access =
+                                vec![quote! { event }, quote! { context }, quote! { state }];
+                            if with_siblings {
+                                access.push(quote! { &mut siblings });
+                            }
+                            let remap = remap_closure_inputs(&action.inputs, access.as_slice())?;
 
                             transition_doc.push_str(" Executes an action.");
 
+                            let (siblings, borrow) = generate_action_states_borrow(
+                                fsm,
+                                ty,
+                                &[(state, quote! { state })],
+                                with_siblings,
+                            );
+                            q.append_all(siblings);
+
                             let body = &action.body;
 
                             quote! {
+                                #borrow
                                 #remap
                                 { #body }
                             }
@@ -383,7 +612,7 @@ pub fn generate_fsm_code(
                         q.append_all(quote! {
                             impl #fsm_generics_impl finny::FsmAction<#fsm_ty #fsm_generics_type, #event_ty, #state_ty > for #ty #fsm_generics_where {
                                 /// Business logic to execute on a transition between states.
-                                fn action<'fsm_event, Q>(event: & #event_ty , context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q >, state: &mut #state_ty)
+                                fn action<'fsm_event, Q>(event: & #event_ty , context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q >, states: &mut #states_store_ty #fsm_generics_type)
                                     where Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>
                                 {
                                     #action_body
@@ -409,36 +638,77 @@ pub fn generate_fsm_code(
 
                         transition_doc.push_str(" Start transition.");
 
+                        let choice = if let Some(ref condition) = s.action.guard {
+                            transition_doc.push_str(" Conditional.");
+
+                            let remap = remap_closure_inputs(
+                                &condition.inputs,
+                                vec![quote! { context }].as_slice(),
+                            )?;
+
+                            let body = &condition.body;
+
+                            quote! {
+                                /// Initial choice condition
+                                fn choice(context: & #ctx_ty) -> bool {
+                                    #remap
+                                    let result = { #body };
+                                    result
+                                }
+                            }
+                        } else {
+                            TokenStream::new()
+                        };
+
                         q.append_all(quote! {
                             impl #fsm_generics_impl finny::FsmTransitionFsmStart<#fsm_ty #fsm_generics_type, #initial_state_ty > for #ty #fsm_generics_where {
-
+                                #choice
                             }
                         });
                     }
 
                     // normal state transition
                     FsmTransitionType::StateTransition(s) => {
-                        let event_ty = &s.event.get_event()?.ty;
+                        let is_change = matches!(s.event, FsmTransitionEvent::Change);
+                        let event_ty = if is_change {
+                            quote! { finny::FsmEvent<#event_enum_ty, #timers_enum_ty> }
+                        } else {
+                            let ty = &s.event.get_event()?.ty;
+                            quote! { #ty }
+                        };
                         let state_from = s.state_from.get_fsm_state()?;
                         let state_to = s.state_to.get_fsm_state()?;
 
-                        transition_doc.push_str(&format!(
-                            " Transition, from state [{}] to state [{}] upon the event [{}].",
-                            tokens_to_string(&state_from.ty),
-                            tokens_to_string(&state_to.ty),
-                            tokens_to_string(&event_ty)
-                        ));
+                        if is_change {
+                            transition_doc.push_str(&format!(
+                                " Transition, from state [{}] to state [{}] when its condition is met.",
+                                tokens_to_string(&state_from.ty),
+                                tokens_to_string(&state_to.ty)
+                            ));
+                        } else {
+                            transition_doc.push_str(&format!(
+                                " Transition, from state [{}] to state [{}] upon the event [{}].",
+                                tokens_to_string(&state_from.ty),
+                                tokens_to_string(&state_to.ty),
+                                tokens_to_string(&event_ty)
+                            ));
+                        }
 
                         if let Some(ref guard) = s.action.guard {
-                            let event_ty = &s.event.get_event()?.ty;
-
                             transition_doc.push_str(" Guarded.");
 
-                            let remap = remap_closure_inputs(
-                                &guard.inputs,
-                                vec![quote! { event }, quote! { context }, quote! { states }]
-                                    .as_slice(),
-                            )?;
+                            let remap = if is_change {
+                                remap_closure_inputs(
+                                    &guard.inputs,
+                                    vec![quote! { context }, quote! { states }].as_slice(),
+                                )?
+                            } else {
+                                remap_closure_inputs(
+                                    &guard.inputs,
+                                    vec![quote! { event }, quote! { context }, quote! { states }]
+                                        .as_slice(),
+                                )?
+                            };
 
                             let body = &guard.body;
 
@@ -461,20 +731,45 @@ pub fn generate_fsm_code(
                         let action_body = if let Some(ref action) = s.action.action {
                             transition_doc.push_str(" Executes an action.");
 
-                            let remap = remap_closure_inputs(
-                                &action.inputs,
+                            let mut access = if is_change {
+                                vec![quote! { context }, quote! { from }, quote! { to }]
+                            } else {
                                 vec![
                                     quote! { event },
                                     quote! { context },
                                     quote! { from },
                                     quote! { to },
                                 ]
-                                .as_slice(),
-                            )?;
+                            };
+                            // the sibling states are optional
+                            let with_siblings = action.inputs.len() == access.len() + 1;
+                            if with_siblings && states_storage_enum {
+                                return Err(syn::Error::new(
+                                    action.span(),
+                                    "Sibling states aren't supported with the enum states storage.",
+                                ));
+                            }
+                            if with_siblings {
+                                access.push(quote! { &mut siblings });
+                            }
+                            let remap = remap_closure_inputs(&action.inputs, access.as_slice())?;
+
+                            let (siblings, borrow) = if states_storage_enum {
+                                (TokenStream::new(), TokenStream::new())
+                            } else {
+                                generate_action_states_borrow(
+                                    fsm,
+                                    ty,
+                                    &[(state_from, quote! { from }), (state_to, quote! { to })],
+                                    with_siblings,
+                                )
+                            };
+                            q.append_all(siblings);
 
                             let body = &action.body;
 
                             quote! {
+                                #borrow
                                 #remap
                                 { #body }
                             }
@@ -485,14 +780,73 @@ pub fn generate_fsm_code(
                         let state_from_ty = &state_from.ty;
                         let state_to_ty = &state_to.ty;
 
+                        // with the enum storage, the target state isn't stored yet while the action runs
+                        let (action_body, action_with_target) = if states_storage_enum
+                            && !action_body.is_empty()
+                        {
+                            let action_with_target = quote! {
+                                /// Business logic to execute on a transition between states, with the target state not stored yet.
+                                fn action_with_target<'fsm_event, Q>(event: & #event_ty , context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q >, from: &mut #state_from_ty, to: &mut #state_to_ty)
+                                    where Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>
+                                {
+                                    #action_body
+                                }
+                            };
+                            (TokenStream::new(), action_with_target)
+                        } else {
+                            (action_body, TokenStream::new())
+                        };
+
+                        let new_target_state = if let Some(ref constructor) =
+                            s.action.state_constructor
+                        {
+                            transition_doc.push_str(" Constructs the target state.");
+
+                            let remap = remap_closure_inputs(
+                                &constructor.inputs,
+                                vec![quote! { event }, quote! { context }].as_slice(),
+                            )?;
+
+                            let body = &constructor.body;
+
+                            q.append_all(quote! {
+                                impl #fsm_generics_impl finny::FsmTransitionConstructor<#fsm_ty #fsm_generics_type, #event_ty, #state_to_ty> for #ty #fsm_generics_where {
+                                    /// Construct the target state from the event.
+                                    fn construct<'fsm_event, Q>(event: & #event_ty, context: &finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q>) -> #state_to_ty
+                                        where Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>
+                                    {
+                                        #remap
+                                        let state = { #body };
+                                        state
+                                    }
+                                }
+                            });
+
+                            quote! {
+                                /// Construct the target state from the event.
+                                fn new_target_state<'fsm_event, Q>(event: & #event_ty, context: &finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q>) -> Option<#state_to_ty>
+                                    where Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>
+                                {
+                                    use finny::FsmTransitionConstructor;
+                                    Some(Self::construct(event, context))
+                                }
+                            }
+                        } else {
+                            TokenStream::new()
+                        };
+
                         let a = quote! {
                             impl #fsm_generics_impl finny::FsmTransitionAction<#fsm_ty #fsm_generics_type, #event_ty, #state_from_ty, #state_to_ty> for #ty #fsm_generics_where {
                                 /// Business logic to execute on a transition between states.
-                                fn action<'fsm_event, Q>(event: & #event_ty , context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q >, from: &mut #state_from_ty, to: &mut #state_to_ty)
+                                fn action<'fsm_event, Q>(event: & #event_ty , context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q >, states: &mut #states_store_ty #fsm_generics_type)
                                     where Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>
                                 {
                                     #action_body
                                 }
+
+                                #action_with_target
+
+                                #new_target_state
                             }
                         };
 
@@ -515,6 +869,75 @@ pub fn generate_fsm_code(
         t
     };
 
+    let delayed_slots_len = match delayed_slots {
+        Some(slots) => quote! { #slots },
+        None => quote! { 0 },
+    };
+
+    let delayed_timers = match delayed_slots {
+        Some(_) => quote! {
+            #[allow(unreachable_patterns)]
+            fn delayed_slot(timer: &Self::Timers) -> Option<finny::FsmDelayedSlot> {
+                match timer {
+                    #timers_enum_ty :: FsmDelayed ( slot ) => Some(*slot),
+                    _ => None,
+                }
+            }
+
+            fn delayed_timer(slot: finny::FsmDelayedSlot) -> Option<Self::Timers> {
+                Some( #timers_enum_ty :: FsmDelayed ( slot ) )
+            }
+        },
+        None => quote! {
+            fn delayed_slot(_timer: &Self::Timers) -> Option<finny::FsmDelayedSlot> {
+                None
+            }
+
+            fn delayed_timer(_slot: finny::FsmDelayedSlot) -> Option<Self::Timers> {
+                None
+            }
+        },
+    };
+
+    let has_change_events = {
+        let mut has_change_events = vec![];
+
+        let own = fsm
+            .fsm
+            .regions
+            .iter()
+            .flat_map(|r| r.transitions.iter())
+            .any(|t| {
+                matches!(
+                    t.ty,
+                    FsmTransitionType::StateTransition(FsmStateTransition {
+                        event: FsmTransitionEvent::Change,
+                        ..
+                    })
+                )
+            });
+        if own {
+            has_change_events.push(quote! { true });
+        }
+
+        for state in fsm.fsm.states.values() {
+            if let FsmStateKind::SubMachine(_) = state.kind {
+                let sub = &state.ty;
+                has_change_events.push(quote! { <#sub as finny::FsmBackend>::has_change_events() });
+            }
+        }
+
+        if has_change_events.is_empty() {
+            TokenStream::new()
+        } else {
+            quote! {
+                fn has_change_events() -> bool {
+                    #(#has_change_events)||*
+                }
+            }
+        }
+    };
+
     let dispatch = {
         let mut regions = TokenStream::new();
         for region in &fsm.fsm.regions {
@@ -534,8 +957,7 @@ pub fn generate_fsm_code(
                     match state_from {
                         FsmTransitionState::None => quote! { finny::FsmCurrentState::Stopped },
                         FsmTransitionState::State(st) => {
-                            let state_ty = FsmTypes::new(&st.ty, &fsm.base.fsm_generics);
-                            let variant = state_ty.get_fsm_no_generics_ty();
+                            let variant = st.get_variant_ty();
                             quote! { finny::FsmCurrentState::State(#states_enum_ty :: #variant) }
                         }
                     }
@@ -555,13 +977,24 @@ pub fn generate_fsm_code(
                         crate::parse::FsmTransitionEvent::Stop => {
                             quote! { ev @ finny::FsmEvent::Stop }
                         }
+                        crate::parse::FsmTransitionEvent::Change => {
+                            quote! { ev @ finny::FsmEvent::Change }
+                        }
                         crate::parse::FsmTransitionEvent::Event(ref ev) => {
-                            let kind = &ev.ty;
+                            let kind = to_variant_ty(&ev.ty);
                             quote! { finny::FsmEvent::Event(#event_enum_ty::#kind(ref ev)) }
                         }
                     }
                 };
 
+                let is_start_transition = matches!(
+                    &transition.ty,
+                    FsmTransitionType::StateTransition(FsmStateTransition {
+                        state_from: FsmTransitionState::None,
+                        ..
+                    })
+                );
+
                 let guard = {
                     let has_guard = match &transition.ty {
                         FsmTransitionType::StateTransition(s) => s.action.guard.is_some(),
@@ -569,7 +1002,11 @@ pub fn generate_fsm_code(
                         | FsmTransitionType::SelfTransition(s) => s.action.guard.is_some(),
                     };
 
-                    if has_guard {
+                    if has_guard && is_start_transition {
+                        quote! {
+                            if <#transition_ty>::execute_choice(&mut ctx, &mut inspect_event_ctx)
+                        }
+                    } else if has_guard {
                         quote! {
                             if <#transition_ty>::execute_guard(&mut ctx, &ev, #region_id, &mut inspect_event_ctx)
                         }
@@ -670,12 +1107,81 @@ pub fn generate_fsm_code(
                     timers_exit
                 };
 
+                let is_state_transition =
+                    matches!(&transition.ty, FsmTransitionType::StateTransition(_));
+
+                let has_constructor = matches!(
+                    &transition.ty,
+                    FsmTransitionType::StateTransition(FsmStateTransition {
+                        action: EventGuardAction {
+                            state_constructor: Some(_),
+                            ..
+                        },
+                        ..
+                    })
+                );
+
+                // the states that are built by their transitions don't need a factory
+                let construct_state = if states_storage_enum
+                    && is_state_transition
+                    && !is_start_transition
+                    && has_constructor
+                {
+                    quote! {
+                        let state_to = {
+                            use finny::FsmTransitionConstructor;
+                            <#transition_ty>::construct_target_state_with(&mut ctx, &ev, #region_id)
+                        };
+                    }
+                } else if states_storage_enum && is_state_transition && !is_start_transition {
+                    quote! {
+                        let state_to = <#transition_ty>::construct_target_state(&mut ctx, &ev, #region_id)?;
+                    }
+                } else {
+                    TokenStream::new()
+                };
+
+                let execute_transition = if states_storage_enum && is_start_transition {
+                    quote! {
+                        <#transition_ty>::execute_transition_replace(&mut ctx, &ev, #region_id, &mut inspect_event_ctx)?;
+                    }
+                } else if states_storage_enum && is_state_transition {
+                    quote! {
+                        <#transition_ty>::execute_transition_replace(&mut ctx, &ev, &event, #region_id, &mut inspect_event_ctx, state_to);
+                    }
+                } else if is_start_transition {
+                    quote! {
+                        <#transition_ty>::execute_transition(&mut ctx, &ev, #region_id, &mut inspect_event_ctx);
+                    }
+                } else {
+                    quote! {
+                        <#transition_ty>::execute_transition(&mut ctx, &ev, &event, #region_id, &mut inspect_event_ctx);
+                    }
+                };
+
+                let transition_kind = match &transition.ty {
+                    _ if is_start_transition => quote! { finny::FsmTransitionKind::Start },
+                    FsmTransitionType::StateTransition(_) => {
+                        quote! { finny::FsmTransitionKind::State }
+                    }
+                    FsmTransitionType::InternalTransition(_) => {
+                        quote! { finny::FsmTransitionKind::Internal }
+                    }
+                    FsmTransitionType::SelfTransition(_) => {
+                        quote! { finny::FsmTransitionKind::SelfTransition }
+                    }
+                };
+
                 let m = quote! {
                     ( #match_state , #match_event ) #guard => {
 
+                        #construct_state
+
                         #timers_exit
 
-                        <#transition_ty>::execute_transition(&mut ctx, &ev, #region_id, &mut inspect_event_ctx);
+                        #execute_transition
+
+                        ctx.transition_taken(#region_id, #transition_kind, core::any::type_name::<#transition_ty>());
 
                         #fsm_sub_entry
 
@@ -715,18 +1221,104 @@ pub fn generate_fsm_code(
                     let fsm_sub = FsmTypes::new(&submachine.ty, &fsm.base.fsm_generics);
                     let kind_variant = fsm_sub.get_fsm_no_generics_ty();
 
+                    // the submachine is in a single region, so the event is moved into it
                     let sub = quote! {
-                        ( finny::FsmCurrentState::State(#states_enum_ty :: #kind_variant), finny::FsmEvent::Event(#event_enum_ty::#kind_variant(ev))  ) => {
-                            return finny::dispatch_to_submachine::<_, #kind, _, _, _>(&mut ctx, finny::FsmEvent::Event(ev.clone()), &mut inspect_event_ctx);
+                        ( finny::FsmCurrentState::State(#states_enum_ty :: #kind_variant), finny::FsmEvent::Event(#event_enum_ty::#kind_variant(_))  ) => {
+                            if let finny::FsmEvent::Event(#event_enum_ty::#kind_variant(ev)) = event {
+                                let result = finny::dispatch_to_submachine::<_, #kind, _, _, _>(&mut ctx, finny::FsmEvent::Event(ev), &mut inspect_event_ctx);
+                                if result.is_ok() {
+                                    ctx.transition_taken(#region_id, finny::FsmTransitionKind::SubMachine, core::any::type_name::<#kind>());
+                                }
+                                return result;
+                            }
                         },
                     };
 
                     sub_matches.append_all(sub);
                 }
 
+                // change events are evaluated by the active submachine first, the parent's
+                // change transitions only if none of the submachine's fired
+                for state in region
+                    .states
+                    .iter()
+                    .filter(|s| matches!(s.kind, FsmStateKind::SubMachine(_)))
+                {
+                    let sub = &state.ty;
+                    let sub_ty = FsmTypes::new(sub, &fsm.base.fsm_generics);
+                    let sub_variant = sub_ty.get_fsm_no_generics_ty();
+
+                    sub_matches.append_all(quote! {
+                        ( finny::FsmCurrentState::State(#states_enum_ty :: #sub_variant), finny::FsmEvent::Change )
+                            if <#sub as finny::FsmBackend>::has_change_events() && match finny::dispatch_to_submachine::<_, #sub, _, _, _>(&mut ctx, finny::FsmEvent::Change, &mut inspect_event_ctx) {
+                                Ok(()) => true,
+                                Err(finny::FsmError::NoTransition) => false,
+                                Err(e) => return Err(e),
+                            } => (),
+                    });
+                }
+
                 sub_matches
             };
 
+            // exit the active state and stop the region
+            let region_stop = {
+                let mut stop = TokenStream::new();
+
+                // drop the exited state, only the active one is stored
+                let drop_state = if states_storage_enum {
+                    let region_field = format_ident!("region_{}", region_id);
+                    let region_ty = ty_append(&states_store_ty, &format!("Region{}", region_id));
+                    quote! {
+                        ctx.backend.states. #region_field = #region_ty::Stopped;
+                    }
+                } else {
+                    TokenStream::new()
+                };
+
+                for state in &region.states {
+                    let ty = &state.ty;
+                    let variant = state.get_variant_ty();
+
+                    let sub_stop = if let FsmStateKind::SubMachine(_) = state.kind {
+                        quote! {
+                            let _ = finny::dispatch_to_submachine::<_, #ty, _, _, _>(&mut ctx, finny::FsmEvent::Stop, &mut inspect_event_ctx);
+                        }
+                    } else {
+                        TokenStream::new()
+                    };
+
+                    let mut timers_exit = TokenStream::new();
+                    for timer in &state.timers {
+                        let timer_field = timer.get_field(&fsm.base);
+                        let timer_ty = timer.get_ty(&fsm.base);
+
+                        timers_exit.append_all(quote! {
+                            {
+                                use finny::FsmTimer;
+                                ctx.backend.states. #timer_field . execute_on_exit( #timers_enum_ty :: #timer_ty , &mut inspect_event_ctx, ctx.timers );
+                            }
+                        });
+                    }
+
+                    stop.append_all(quote! {
+                        ( finny::FsmCurrentState::State(#states_enum_ty :: #variant), finny::FsmEvent::Stop ) => {
+                            #sub_stop
+
+                            #timers_exit
+
+                            <#ty>::execute_on_exit(&mut ctx, &event, #region_id);
+                            #drop_state
+                            ctx.backend.current_states[#region_id] = finny::FsmCurrentState::Stopped;
+
+                            ctx.transition_taken(#region_id, finny::FsmTransitionKind::Stop, core::any::type_name::<#ty>());
+                        },
+                    });
+                }
+
+                stop
+            };
+
             // match and dispatch timer events
             let timers = {
                 let mut timer_dispatch = TokenStream::new();
@@ -739,8 +1331,13 @@ pub fn generate_fsm_code(
                         timer_dispatch.append_all(quote! {
                             (_, finny::FsmEvent::Timer( timer_id @ #timers_enum_ty :: #timer_ty )) => {
                                 {
-                                    use finny::FsmTimer;
-                                    < #timer_ty #fsm_generics_type > :: execute_trigger(*timer_id, &mut ctx, &mut inspect_event_ctx);
+                                    use finny::{FsmRejectedEvents, FsmTimer};
+                                    if let Err((e, ev)) = < #timer_ty #fsm_generics_type > :: execute_trigger(*timer_id, &mut ctx, &mut inspect_event_ctx) {
+                                        if let Some(rejected) = ctx.rejected.as_deref_mut() {
+                                            rejected.reject(ev);
+                                        }
+                                        return Err(e);
+                                    }
                                 }
                             },
                         });
@@ -773,6 +1370,11 @@ pub fn generate_fsm_code(
 
                     #region_transitions
 
+                    #region_stop
+
+                    // stopping a stopped region is a no-op
+                    (finny::FsmCurrentState::Stopped, finny::FsmEvent::Stop) => (),
+
                     // do not dispatch timers if the machine is stopped
                     (finny::FsmCurrentState::Stopped, finny::FsmEvent::Timer(_)) => (),
 
@@ -794,6 +1396,16 @@ pub fn generate_fsm_code(
                 type States = #states_store_ty #fsm_generics_type;
                 type Events = #event_enum_ty;
                 type Timers = #timers_enum_ty;
+                type Outputs = #outputs_ty;
+                type DelayedSlots = [Option<finny::FsmDelayedEvent<Self>>; #delayed_slots_len];
+
+                fn event_name(event: &Self::Events) -> &'static str {
+                    event.event_name()
+                }
+
+                #delayed_timers
+
+                #has_change_events
 
                 fn dispatch_event<Q, I, T>(mut ctx: finny::DispatchContext<Self, Q, I, T>, event: finny::FsmEvent<Self::Events, Self::Timers>) -> finny::FsmDispatchResult
                     where Q: finny::FsmEventQueue<Self>,
@@ -834,8 +1446,15 @@ pub fn generate_fsm_code(
         for (ty, state) in fsm.fsm.states.iter() {
             let remap_closure = |c: &Option<syn::ExprClosure>| -> syn::Result<TokenStream> {
                 if let Some(c) = &c {
-                    let remap =
-                        remap_closure_inputs(&c.inputs, &[quote! { self }, quote! { context }])?;
+                    // the triggering event is optional
+                    let remap = if c.inputs.len() == 3 {
+                        remap_closure_inputs(
+                            &c.inputs,
+                            &[quote! { self }, quote! { context }, quote! { fsm_event }],
+                        )?
+                    } else {
+                        remap_closure_inputs(&c.inputs, &[quote! { self }, quote! { context }])?
+                    };
                     let b = &c.body;
 
                     let q = quote! {
@@ -851,19 +1470,18 @@ pub fn generate_fsm_code(
             let on_entry = remap_closure(&state.on_entry_closure)?;
             let on_exit = remap_closure(&state.on_exit_closure)?;
 
-            let state_ty = FsmTypes::new(ty, &fsm.base.fsm_generics);
-            let variant = state_ty.get_fsm_no_generics_ty();
+            let variant = state.get_variant_ty();
 
             let state = quote! {
 
                 impl #fsm_generics_impl finny::FsmState<#fsm_ty #fsm_generics_type> for #ty #fsm_generics_where {
                     /// Code to execute on entering a state that fsm just transitioned to.
-                    fn on_entry<'fsm_event, Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>>(&mut self, context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q>) {
+                    fn on_entry<'fsm_event, Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>>(&mut self, context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q>, fsm_event: &finny::FsmEvent<#event_enum_ty, #timers_enum_ty>) {
                         #on_entry
                     }
 
                     /// Code to execute when transitioning out of the source state.
-                    fn on_exit<'fsm_event, Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>>(&mut self, context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q>) {
+                    fn on_exit<'fsm_event, Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>>(&mut self, context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q>, fsm_event: &finny::FsmEvent<#event_enum_ty, #timers_enum_ty>) {
                         #on_exit
                     }
 
@@ -1027,6 +1645,16 @@ pub fn generate_fsm_code(
             }
         }
 
+        let is_delayed_event = if delayed_slots.is_some() {
+            enum_variants.push(quote! {
+                /// the timers of the delayed events
+                FsmDelayed ( finny::FsmDelayedSlot )
+            });
+            quote! { matches!(self, #timers_enum_ty :: FsmDelayed ( _ )) }
+        } else {
+            quote! { false }
+        };
+
         let variants = {
             let mut t = TokenStream::new();
             t.append_separated(&enum_variants, quote! { , });
@@ -1039,6 +1667,13 @@ pub fn generate_fsm_code(
             pub enum #timers_enum_ty {
                 #variants
             }
+
+            impl #timers_enum_ty {
+                /// Is this the timer of an event delayed by the actions?
+                pub fn is_delayed_event(&self) -> bool {
+                    #is_delayed_event
+                }
+            }
         });
 
         let submachine_iters: Vec<_> = submachines
@@ -1066,7 +1701,8 @@ pub fn generate_fsm_code(
                     quote! {
                         #i if self.#field.is_some() => {
                             if let Some(ref mut iter) = self.#field {
-                                let r = iter.next();
+                                // only the top machine keeps the delayed events
+                                let r = iter.find(|t| !t.is_delayed_event());
                                 if let Some(r) = r {
                                     let r = r.into();
                                     return Some(r);
@@ -1081,6 +1717,19 @@ pub fn generate_fsm_code(
                 }),
             quote! { , },
         );
+        if let Some(slots) = delayed_slots {
+            let delayed_start = our_timers.len() + submachine_iters.len();
+            if delayed_start > 0 {
+                enum_iter_matches.append_all(quote! { , });
+            }
+            enum_iter_matches.append_all(quote! {
+                p if (#delayed_start..#delayed_start + (#slots)).contains(&p) => {
+                    self.position += 1;
+                    let slot = finny::FsmDelayedSlot::new(p - #delayed_start);
+                    Some(#timers_enum_ty :: FsmDelayed ( slot ))
+                }
+            });
+        }
 
         let mut submachine_iter_struct = TokenStream::new();
         submachine_iter_struct.append_separated(
@@ -1164,6 +1813,11 @@ pub fn generate_fsm_code(
                 #field: #ty < TTimerStorage >
             }
         }));
+        if let Some(slots) = delayed_slots {
+            timers_storage_struct_fields.push(quote! {
+                fsm_delayed: [Option < TTimerStorage >; #slots]
+            });
+        }
         let mut fields = TokenStream::new();
         fields.append_separated(timers_storage_struct_fields, quote! { , });
 
@@ -1180,6 +1834,11 @@ pub fn generate_fsm_code(
                 #field: #ty :: default()
             }
         }));
+        if delayed_slots.is_some() {
+            new_fields_vec.push(quote! {
+                fsm_delayed: core::array::from_fn(|_| None)
+            });
+        }
         let mut new_fields = TokenStream::new();
         new_fields.append_separated(new_fields_vec, quote! { , });
 
@@ -1200,6 +1859,11 @@ pub fn generate_fsm_code(
                 }
             }
         }));
+        if delayed_slots.is_some() {
+            timers_storage_matches.push(quote! {
+                #timers_enum_ty :: FsmDelayed (ref slot) => &mut self.fsm_delayed[slot.index()]
+            });
+        }
 
         let matches = if timers_storage_matches.is_empty() {
             quote! {
@@ -1276,9 +1940,12 @@ pub fn generate_fsm_code(
                         fn reset<I>(backend: &mut finny::FsmBackendImpl< #fsm_ty #fsm_generics_type >, inspect_event_ctx: &mut I)
                             where I: finny::Inspect
                         {
-                            let sub_fsm: &mut #sub_ty = backend.states.as_mut();
-                            sub_fsm.backend.current_states = Default::default();
-                            inspect_event_ctx.info("Setting the state of the submachine to Start.");
+                            use finny::FsmStatesTryAsRef;
+                            let sub_fsm: Option<&mut #sub_ty> = backend.states.try_as_mut();
+                            if let Some(sub_fsm) = sub_fsm {
+                                sub_fsm.backend.current_states = Default::default();
+                                inspect_event_ctx.info("Setting the state of the submachine to Start.");
+                            }
                         }
                     }
 
@@ -1323,3 +1990,116 @@ pub fn generate_fsm_code(
 
     Ok(q)
 }
+
+/// Borrows the states of an action from the machine's states storage. With siblings, the other
+/// states are borrowed disjointly into a generated struct that is named after the transition.
+fn generate_action_states_borrow(
+    fsm: &FsmFnInput,
+    transition_ty: &syn::Type,
+    borrowed: &[(&FsmState, TokenStream)],
+    with_siblings: bool,
+) -> (TokenStream, TokenStream) {
+    let fsm_ty = &fsm.base.fsm_ty;
+    let states_store_ty = ty_append(fsm_ty, "States");
+    let (_, fsm_generics_type, _) = fsm.base.fsm_generics.split_for_impl();
+
+    if !with_siblings {
+        let borrow = match borrowed {
+            [(state, binding)] => {
+                let state_ty = &state.ty;
+                quote! {
+                    let #binding: &mut #state_ty = states.as_mut();
+                }
+            }
+            [(from, from_binding), (to, to_binding)] => {
+                let from_ty = &from.ty;
+                let to_ty = &to.ty;
+                quote! {
+                    let (#from_binding, #to_binding) = finny::FsmStateTransitionAsMut::<#from_ty, #to_ty>::as_state_transition_mut(states);
+                }
+            }
+            _ => TokenStream::new(),
+        };
+
+        return (TokenStream::new(), borrow);
+    }
+
+    let siblings_ty = ty_append(transition_ty, "Siblings");
+
+    let mut generics = fsm.base.fsm_generics.clone();
+    generics.params.insert(0, parse_quote! { 'fsm_siblings });
+    let (generics_impl, generics_type, generics_where) = generics.split_for_impl();
+
+    let mut pattern = TokenStream::new();
+    for (state, binding) in borrowed {
+        let field = &state.state_storage_field;
+        pattern.append_all(quote! { #field: #binding, });
+    }
+
+    let mut siblings = TokenStream::new();
+    let mut fields = TokenStream::new();
+    let mut accessors = TokenStream::new();
+
+    let mut states: Vec<_> = fsm
+        .fsm
+        .states
+        .values()
+        .filter(|s| {
+            !borrowed
+                .iter()
+                .any(|(b, _)| b.state_storage_field == s.state_storage_field)
+        })
+        .collect();
+    states.sort_by_key(|s| s.state_storage_field.to_string());
+
+    for state in states {
+        let state_ty = &state.ty;
+        let field = &state.state_storage_field;
+        let binding = format_ident!("sibling_{}", field);
+
+        pattern.append_all(quote! { #field: #binding, });
+        siblings.append_all(quote! { #field: #binding, });
+        fields.append_all(quote! { #field: &'fsm_siblings mut #state_ty, });
+        accessors.append_all(quote! {
+            impl #generics_impl core::convert::AsRef<#state_ty> for #siblings_ty #generics_type #generics_where {
+                /// Convert to a shared reference.
+                fn as_ref(&self) -> & #state_ty {
+                    self. #field
+                }
+            }
+
+            impl #generics_impl core::convert::AsMut<#state_ty> for #siblings_ty #generics_type #generics_where {
+                /// Convert to a mutable reference.
+                fn as_mut(&mut self) -> &mut #state_ty {
+                    self. #field
+                }
+            }
+        });
+    }
+
+    let doc = format!(
+        " The other states of [{}], borrowed by the action of [{}].",
+        tokens_to_string(fsm_ty),
+        tokens_to_string(transition_ty)
+    );
+
+    let definition = quote! {
+        #[doc = #doc]
+        pub struct #siblings_ty #generics_impl #generics_where {
+            #fields
+            _fsm: core::marker::PhantomData<&'fsm_siblings #fsm_ty #fsm_generics_type>
+        }
+
+        #accessors
+    };
+
+    let borrow = quote! {
+        let #states_store_ty { #pattern .. } = states;
+        let mut siblings = #siblings_ty {
+            #siblings
+            _fsm: core::marker::PhantomData
+        };
+    };
+
+    (definition, borrow)
+}
@@ -29,7 +29,7 @@ fn to_info_state(s: &FsmTransitionState, fsm: &FsmFnInput) -> FinnyStateKind {
                 ..
             },
         ) => FinnyStateKind::State(FinnyState {
-            state_id: ty_to_string(&s.ty),
+            state_id: tokens_to_string(&s.get_variant_ty()),
             timers: s
                 .timers
                 .iter()
@@ -115,6 +115,7 @@ fn to_info(fsm: &FsmFnInput) -> FinnyFsm {
                                 let event = match event {
                                     crate::parse::FsmTransitionEvent::Stop => FinnyEvent::Stop,
                                     crate::parse::FsmTransitionEvent::Start => FinnyEvent::Start,
+                                    crate::parse::FsmTransitionEvent::Change => FinnyEvent::Change,
                                     crate::parse::FsmTransitionEvent::Event(ev) => {
                                         FinnyEvent::Event(tokens_to_string(&ev.ty))
                                     }
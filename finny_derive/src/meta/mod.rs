@@ -55,6 +55,7 @@ pub struct FinnyTransition {
 pub enum FinnyEvent {
     Start,
     Stop,
+    Change,
     Event(String),
 }
 
@@ -40,6 +40,7 @@ pub fn to_plant_uml(fsm: &FinnyFsm) -> Result<(String, TokenStream), std::fmt::E
             let event = match transition.event {
                 super::FinnyEvent::Start => "Start".to_string(),
                 super::FinnyEvent::Stop => "Stop".to_string(),
+                super::FinnyEvent::Change => "Change".to_string(),
                 super::FinnyEvent::Event(ref ev) => ev.clone(),
             };
 
@@ -6,7 +6,7 @@ use syn::{spanned::Spanned, Error, GenericArgument, ItemFn};
 use crate::{
     parse_blocks::{decode_blocks, FsmBlock},
     parse_fsm::{FsmCodegenOptions, FsmParser},
-    utils::{to_field_name, ty_append},
+    utils::{strip_generics, to_field_name, to_variant_ty, ty_append},
 };
 
 pub struct FsmFnInput {
@@ -164,17 +164,31 @@ impl FsmFnInput {
 #[derive(Debug)]
 pub struct FsmDeclarations {
     pub initial_states: Vec<syn::Type>,
+    pub initial_choices: Vec<FsmInitialChoice>,
     pub states: HashMap<syn::Type, FsmState>,
     pub events: HashMap<syn::Type, FsmEvent>,
+    pub outputs_ty: Option<syn::Type>,
+    pub delayed_slots: Option<syn::Expr>,
     pub transitions: Vec<FsmTransition>,
 }
 
+/// A conditional initial state, the unconditional one is kept in the initial states.
+#[derive(Debug, Clone)]
+pub struct FsmInitialChoice {
+    pub condition: syn::ExprClosure,
+    pub state: syn::Type,
+}
+
 #[derive(Debug)]
 pub struct ValidatedFsm {
     pub codegen_options: FsmCodegenOptions,
     pub regions: Vec<FsmRegion>,
     pub states: HashMap<syn::Type, FsmState>,
     pub events: HashMap<syn::Type, FsmEvent>,
+    /// The type of the outputs that the actions emit.
+    pub outputs_ty: Option<syn::Type>,
+    /// The number of the slots for the delayed events, if the machine delays any.
+    pub delayed_slots: Option<syn::Expr>,
 }
 
 #[derive(Debug)]
@@ -196,6 +210,8 @@ pub enum FsmTransitionEvent {
     #[allow(unused)]
     Stop,
     Start,
+    /// Evaluated after every run-to-completion step.
+    Change,
     Event(FsmEvent),
 }
 
@@ -289,6 +305,17 @@ pub struct FsmState {
     pub timers: Vec<FsmTimer>,
 }
 
+impl FsmState {
+    /// The variant of the state in the generated enums. Submachines share the generics of the
+    /// parent, so their generic arguments are left out.
+    pub fn get_variant_ty(&self) -> syn::Type {
+        match self.kind {
+            FsmStateKind::Normal => to_variant_ty(&self.ty),
+            FsmStateKind::SubMachine(_) => strip_generics(self.ty.clone()),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FsmTimer {
     pub id: usize,
@@ -315,6 +342,8 @@ impl FsmTimer {
 pub struct FsmEvent {
     pub ty: syn::Type,
     pub transitions: Vec<FsmEventTransition>,
+    /// Set with the `#[priority(n)]` attribute, used by the priority queues.
+    pub priority: Option<syn::Expr>,
 }
 
 #[derive(Debug, Clone)]
@@ -332,6 +361,8 @@ pub struct EventGuardAction {
     pub guard: Option<syn::ExprClosure>,
     pub action: Option<syn::ExprClosure>,
     pub type_hint: Option<syn::Type>,
+    /// Builds the target state from the event on every transition.
+    pub state_constructor: Option<syn::ExprClosure>,
 }
 
 impl FsmDeclarations {
@@ -1,3 +1,4 @@
+use quote::ToTokens;
 use syn::{spanned::Spanned, AngleBracketedGenericArguments, Expr, ExprMethodCall};
 
 use crate::parse::FsmFnBase;
@@ -116,6 +117,10 @@ pub fn get_generics(
                 syn::GenericArgument::Type(ty) => {
                     ret.push(ty.clone());
                 }
+                // the const arguments are kept as tokens, like the ones that parse as paths
+                syn::GenericArgument::Const(expr) => {
+                    ret.push(syn::Type::Verbatim(expr.to_token_stream()));
+                }
                 _ => {
                     return Err(syn::Error::new(arg.span(), "Unsupported."));
                 }
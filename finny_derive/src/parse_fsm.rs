@@ -1,34 +1,48 @@
 use std::collections::HashMap;
 
 use proc_macro2::Span;
+use quote::ToTokens;
 use syn::{spanned::Spanned, ExprMethodCall, ItemFn, Type};
 
 use crate::{
     parse::{
-        EventGuardAction, FsmDeclarations, FsmEvent, FsmEventTransition, FsmFnBase, FsmState,
-        FsmStateAction, FsmStateKind, FsmStateTransition, FsmSubMachineOptions, FsmTimer,
-        FsmTransition, FsmTransitionEvent, FsmTransitionState, FsmTransitionType, ValidatedFsm,
+        EventGuardAction, FsmDeclarations, FsmEvent, FsmEventTransition, FsmFnBase,
+        FsmInitialChoice, FsmState, FsmStateAction, FsmStateKind, FsmStateTransition,
+        FsmSubMachineOptions, FsmTimer, FsmTransition, FsmTransitionEvent, FsmTransitionState,
+        FsmTransitionType, ValidatedFsm,
     },
     parse_blocks::{get_generics, FsmBlock},
-    utils::{assert_no_generics, get_closure, to_field_name},
+    utils::{
+        assert_supported_ty, async_closure, get_closure, strip_turbofish, to_field_name,
+        to_variant_ty,
+    },
     validation::create_regions,
 };
 
 #[derive(Copy, Clone, Debug)]
 pub struct FsmCodegenOptions {
     pub event_debug: bool,
+    /// Store only the active state of each region, in a generated enum.
+    pub states_storage_enum: bool,
 }
 
 impl FsmCodegenOptions {
     pub fn new() -> Self {
-        Self { event_debug: false }
+        Self {
+            event_debug: false,
+            states_storage_enum: false,
+        }
     }
 }
 
 pub struct FsmParser {
     initial_states: Vec<syn::Type>,
+    initial_choices: Vec<FsmInitialChoice>,
+    change_transitions: Vec<FsmEventTransition>,
     states: HashMap<Type, FsmState>,
     events: HashMap<Type, FsmEvent>,
+    outputs_ty: Option<Type>,
+    delayed_slots: Option<syn::Expr>,
     options: FsmCodegenOptions,
     base: FsmFnBase,
     timer_id: usize,
@@ -38,8 +52,12 @@ impl FsmParser {
     pub fn new(base: FsmFnBase) -> Self {
         FsmParser {
             initial_states: vec![],
+            initial_choices: vec![],
+            change_transitions: vec![],
             states: HashMap::new(),
             events: HashMap::new(),
+            outputs_ty: None,
+            delayed_slots: None,
             options: FsmCodegenOptions::new(),
             base,
             timer_id: 1,
@@ -67,12 +85,42 @@ impl FsmParser {
                         }] => {
                             self.options.event_debug = true;
                         }
+                        [MethodOverviewRef {
+                            name: "outputs",
+                            generics: [ty],
+                            ..
+                        }] => {
+                            if self.outputs_ty.is_some() {
+                                return Err(syn::Error::new(ty.span(), "Duplicate outputs!"));
+                            }
+                            self.outputs_ty = Some(ty.clone());
+                        }
+                        [MethodOverviewRef {
+                            name: "delayed_events",
+                            generics: [slots],
+                            ..
+                        }] => {
+                            if self.delayed_slots.is_some() {
+                                return Err(syn::Error::new(
+                                    slots.span(),
+                                    "Duplicate delayed_events!",
+                                ));
+                            }
+                            self.delayed_slots = Some(syn::parse2(slots.to_token_stream())?);
+                        }
+                        [MethodOverviewRef {
+                            name: "states_storage_enum",
+                            generics: [],
+                            ..
+                        }] => {
+                            self.options.states_storage_enum = true;
+                        }
                         [MethodOverviewRef {
                             name: "initial_state",
                             generics: [ty],
                             ..
                         }] => {
-                            assert_no_generics(ty)?;
+                            assert_supported_ty(ty)?;
                             if !self.initial_states.is_empty() {
                                 return Err(syn::Error::new(ty.span(), "Duplicate initial_state!"));
                             }
@@ -93,7 +141,7 @@ impl FsmParser {
                             match ty_tuple {
                                 Type::Tuple(tuple) => {
                                     for ty in &tuple.elems {
-                                        assert_no_generics(ty)?;
+                                        assert_supported_ty(ty)?;
                                         self.initial_states.push(ty.clone());
                                     }
                                 }
@@ -106,6 +154,21 @@ impl FsmParser {
                             }
                         }
 
+                        [MethodOverviewRef {
+                            name: "initial_choice",
+                            generics: [],
+                            call,
+                        }, st @ ..] => {
+                            if !self.initial_states.is_empty() {
+                                return Err(syn::Error::new(
+                                    call.span(),
+                                    "Duplicate initial_state!",
+                                ));
+                            }
+
+                            self.initial_choice_parser(st)?;
+                        }
+
                         [MethodOverviewRef {
                             name: "sub_machine",
                             generics: [ty_sub_fsm],
@@ -180,6 +243,8 @@ impl FsmParser {
                             ));
                         }
                     }
+
+                    self.attributes_parser(&mc.expr_call.attrs, &methods)?;
                 }
             }
         }
@@ -187,6 +252,47 @@ impl FsmParser {
         Ok(())
     }
 
+    /// The `#[priority(n)]` attribute on a statement sets the priority of its event.
+    fn attributes_parser(
+        &mut self,
+        attrs: &[syn::Attribute],
+        methods: &[MethodOverviewRef<'_>],
+    ) -> syn::Result<()> {
+        for attr in attrs {
+            if !attr.path().is_ident("priority") {
+                return Err(syn::Error::new(attr.span(), "Unsupported attribute."));
+            }
+
+            let priority: syn::Expr = attr.parse_args()?;
+
+            let ty_event = methods.iter().find_map(|m| match m {
+                MethodOverviewRef {
+                    name: "on_event",
+                    generics: [ty_event],
+                    ..
+                } => Some(ty_event),
+                _ => None,
+            });
+
+            let event = match ty_event.and_then(|ty| self.events.get_mut(ty)) {
+                Some(event) => event,
+                None => {
+                    return Err(syn::Error::new(
+                        attr.span(),
+                        "The priority can only be set on the transitions of an event.",
+                    ));
+                }
+            };
+
+            if event.priority.is_some() {
+                return Err(syn::Error::new(attr.span(), "Duplicate priority!"));
+            }
+            event.priority = Some(priority);
+        }
+
+        Ok(())
+    }
+
     fn parse_event_guard_action(
         event_method_calls: &[MethodOverviewRef],
     ) -> syn::Result<EventGuardAction> {
@@ -194,6 +300,7 @@ impl FsmParser {
             guard: None,
             action: None,
             type_hint: None,
+            state_constructor: None,
         };
 
         for method in event_method_calls {
@@ -216,6 +323,18 @@ impl FsmParser {
 
                     guard_action.action = Some(closure.clone());
                 }
+                MethodOverviewRef {
+                    name: "action_async",
+                    ..
+                } => {
+                    let closure = get_closure(method.call)?;
+
+                    if guard_action.action.is_some() {
+                        return Err(syn::Error::new(closure.span(), "Duplicate 'action'!"));
+                    }
+
+                    guard_action.action = Some(async_closure(closure)?);
+                }
                 MethodOverviewRef {
                     name: "with_transition_ty",
                     generics: [transition_ty],
@@ -256,6 +375,21 @@ impl FsmParser {
                     Self::parse_event_guard_action(ev)?,
                 ));
             }
+            [MethodOverviewRef {
+                name: "transition_to_with",
+                generics: [ty_to],
+                call,
+            }, ev @ ..] => {
+                let constructor = get_closure(call)?;
+                let mut guard_action = Self::parse_event_guard_action(ev)?;
+                guard_action.state_constructor = Some(constructor.clone());
+
+                event.transitions.push(FsmEventTransition::State(
+                    state.ty.clone(),
+                    ty_to.clone(),
+                    guard_action,
+                ));
+            }
             [MethodOverviewRef {
                 name: "internal_transition",
                 generics: [],
@@ -297,7 +431,7 @@ impl FsmParser {
         let mut transitions = vec![];
 
         if self.initial_states.is_empty() {
-            return Err(syn::Error::new(input_fn.span(), "Missing the initial state declaration! Use the method 'initial_state' or 'initial_states'."));
+            return Err(syn::Error::new(input_fn.span(), "Missing the initial state declaration! Use the method 'initial_state', 'initial_states' or 'initial_choice'."));
         }
 
         // build and validate the transitions table
@@ -317,6 +451,24 @@ impl FsmParser {
                 }
             }
 
+            // conditional start transitions, have to be matched before the unconditional one
+            for choice in &self.initial_choices {
+                let fsm_state = self.states.get(&choice.state).ok_or(syn::Error::new(choice.state.span(), "The initial state is not refered in the builder. Use the 'state' method on the builder."))?;
+
+                transitions.push(FsmTransition {
+                    transition_ty: generate_transition_ty(&self.base, &mut i, &None),
+                    ty: FsmTransitionType::StateTransition(FsmStateTransition {
+                        action: EventGuardAction {
+                            guard: Some(choice.condition.clone()),
+                            ..Default::default()
+                        },
+                        event: FsmTransitionEvent::Start,
+                        state_from: FsmTransitionState::None,
+                        state_to: FsmTransitionState::State(fsm_state.clone()),
+                    }),
+                });
+            }
+
             // start transition
             for initial_state in &self.initial_states {
                 let fsm_initial_state = self.states.get(initial_state).ok_or(syn::Error::new(initial_state.span(), "The initial state is not refered in the builder. Use the 'state' method on the builder."))?;
@@ -332,6 +484,33 @@ impl FsmParser {
                 });
             }
 
+            for t in &self.change_transitions {
+                if let FsmEventTransition::State(from, to, action) = t {
+                    let from = self
+                        .states
+                        .get(from)
+                        .ok_or(syn::Error::new(from.span(), "State not found."))?;
+                    let to = self
+                        .states
+                        .get(to)
+                        .ok_or(syn::Error::new(to.span(), "State not found."))?;
+
+                    transitions.push(FsmTransition {
+                        transition_ty: generate_transition_ty(
+                            &self.base,
+                            &mut i,
+                            &action.type_hint,
+                        ),
+                        ty: FsmTransitionType::StateTransition(FsmStateTransition {
+                            action: action.clone(),
+                            state_from: FsmTransitionState::State(from.clone()),
+                            state_to: FsmTransitionState::State(to.clone()),
+                            event: FsmTransitionEvent::Change,
+                        }),
+                    });
+                }
+            }
+
             for (_ty, ev) in self.events.iter() {
                 for t in &ev.transitions {
                     match t {
@@ -404,8 +583,11 @@ impl FsmParser {
 
         let dec = FsmDeclarations {
             initial_states: self.initial_states,
+            initial_choices: self.initial_choices,
             states: self.states,
             events: self.events,
+            outputs_ty: self.outputs_ty,
+            delayed_slots: self.delayed_slots,
             transitions,
         };
 
@@ -414,6 +596,68 @@ impl FsmParser {
         Ok(regions)
     }
 
+    fn initial_choice_parser(&mut self, st: &[MethodOverviewRef]) -> syn::Result<()> {
+        for method in st {
+            if !self.initial_states.is_empty() {
+                return Err(syn::Error::new(
+                    method.call.span(),
+                    "The 'otherwise' state has to be the last one.",
+                ));
+            }
+
+            match method {
+                MethodOverviewRef {
+                    name: "when",
+                    generics: [],
+                    call,
+                } => {
+                    let args: Vec<_> = call.args.iter().collect();
+                    match args.as_slice() {
+                        [syn::Expr::Closure(condition), syn::Expr::Path(state)] => {
+                            let state = strip_turbofish(Type::Path(syn::TypePath {
+                                qself: state.qself.clone(),
+                                path: state.path.clone(),
+                            }));
+                            assert_supported_ty(&state)?;
+                            self.initial_choices.push(FsmInitialChoice {
+                                condition: condition.clone(),
+                                state,
+                            });
+                        }
+                        _ => {
+                            return Err(syn::Error::new(
+                                call.span(),
+                                "Expected a condition closure and the state.",
+                            ));
+                        }
+                    }
+                }
+                MethodOverviewRef {
+                    name: "otherwise",
+                    generics: [ty],
+                    ..
+                } => {
+                    assert_supported_ty(ty)?;
+                    self.initial_states.push(ty.clone());
+                }
+                _ => {
+                    return Err(syn::Error::new(method.call.span(), "Unsupported method."));
+                }
+            }
+        }
+
+        if self.initial_states.is_empty() {
+            return Err(syn::Error::new(
+                st.first()
+                    .map(|m| m.call.span())
+                    .unwrap_or(Span::call_site()),
+                "Missing the 'otherwise' state of the initial choice.",
+            ));
+        }
+
+        Ok(())
+    }
+
     fn state_builder_parser(
         &mut self,
         ty_state: &syn::Type,
@@ -421,9 +665,13 @@ impl FsmParser {
         is_sub_fsm: bool,
     ) -> syn::Result<()> {
         if !is_sub_fsm {
-            assert_no_generics(ty_state)?;
+            assert_supported_ty(ty_state)?;
         }
-        let field_name = to_field_name(ty_state);
+        let field_name = if is_sub_fsm {
+            to_field_name(ty_state)
+        } else {
+            to_field_name(&to_variant_ty(ty_state))
+        };
         let state = self.states.entry(ty_state.clone()).or_insert(FsmState {
             ty: ty_state.clone(),
             on_entry_closure: None,
@@ -447,6 +695,17 @@ impl FsmParser {
                     }
                     state.on_entry_closure = Some(closure.clone());
                 }
+                MethodOverviewRef {
+                    name: "on_entry_async",
+                    ..
+                } => {
+                    let closure = get_closure(method.call)?;
+
+                    if state.on_entry_closure.is_some() {
+                        return Err(syn::Error::new(closure.span(), "Duplicate 'on_entry'!"));
+                    }
+                    state.on_entry_closure = Some(async_closure(closure)?);
+                }
                 MethodOverviewRef {
                     name: "on_exit", ..
                 } => {
@@ -462,11 +721,12 @@ impl FsmParser {
                     generics: [ty_event],
                     ..
                 } => {
-                    assert_no_generics(ty_event)?;
+                    assert_supported_ty(ty_event)?;
 
                     let event = self.events.entry(ty_event.clone()).or_insert(FsmEvent {
                         ty: ty_event.clone(),
                         transitions: vec![],
+                        priority: None,
                     });
 
                     let other_method_calls = &st[(i + 1)..];
@@ -474,6 +734,44 @@ impl FsmParser {
 
                     break;
                 }
+                MethodOverviewRef {
+                    name: "when",
+                    generics: [],
+                    ..
+                } => {
+                    let condition = get_closure(method.call)?;
+
+                    match &st[(i + 1)..] {
+                        [MethodOverviewRef {
+                            name: "transition_to",
+                            generics: [ty_to],
+                            ..
+                        }, ev @ ..] => {
+                            let mut guard_action = Self::parse_event_guard_action(ev)?;
+                            if let Some(ref guard) = guard_action.guard {
+                                return Err(syn::Error::new(
+                                    guard.span(),
+                                    "The condition of 'when' is already the guard of this transition.",
+                                ));
+                            }
+                            guard_action.guard = Some(condition.clone());
+
+                            self.change_transitions.push(FsmEventTransition::State(
+                                state.ty.clone(),
+                                ty_to.clone(),
+                                guard_action,
+                            ));
+                        }
+                        _ => {
+                            return Err(syn::Error::new(
+                                method.call.span(),
+                                "Expected a 'transition_to' after the condition.",
+                            ));
+                        }
+                    }
+
+                    break;
+                }
                 MethodOverviewRef {
                     name: "on_entry_start_timer",
                     generics: [],
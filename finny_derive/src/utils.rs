@@ -1,5 +1,5 @@
-use proc_macro2::{Span, TokenStream};
-use quote::quote;
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
 use syn::{spanned::Spanned, PatIdent};
 
 use crate::parse::{FsmState, FsmTransitionState};
@@ -42,6 +42,46 @@ pub fn remap_closure_inputs(
     Ok(q)
 }
 
+/// Turns the closure of an async action into a regular one, which spawns the future that the
+/// closure's body evaluates to. The body of an `async` closure becomes the future, so it can
+/// only use what it owns. The context is always the second argument.
+pub fn async_closure(closure: &syn::ExprClosure) -> syn::Result<syn::ExprClosure> {
+    let mut closure = closure.clone();
+    if closure.asyncness.take().is_some() {
+        let body = &closure.body;
+        closure.body = Box::new(syn::parse_quote! {
+            async move { #body }
+        });
+        closure.capture = None;
+    }
+
+    let span = closure.span();
+    let ctx = match closure.inputs.iter_mut().nth(1) {
+        Some(syn::Pat::Ident(PatIdent { ident, .. })) => ident.clone(),
+        Some(input @ syn::Pat::Wild(_)) => {
+            let ident = syn::Ident::new("__finny_ctx", input.span());
+            *input = syn::parse_quote!(#ident);
+            ident
+        }
+        _ => {
+            return Err(syn::Error::new(
+                span,
+                "Expected the context as the second closure argument.",
+            ))
+        }
+    };
+
+    let body = &closure.body;
+    closure.body = Box::new(syn::parse_quote! {
+        {
+            let __finny_task = #body;
+            #ctx.spawn_async(__finny_task);
+        }
+    });
+
+    Ok(closure)
+}
+
 pub fn get_closure(call: &syn::ExprMethodCall) -> syn::Result<&syn::ExprClosure> {
     match call.args.first() {
         Some(syn::Expr::Closure(closure)) => Ok(closure),
@@ -49,6 +89,19 @@ pub fn get_closure(call: &syn::ExprMethodCall) -> syn::Result<&syn::ExprClosure>
     }
 }
 
+/// Removes the turbofish, so that `Buffer::<64>` matches the type `Buffer<64>`.
+pub fn strip_turbofish(mut ty: syn::Type) -> syn::Type {
+    if let syn::Type::Path(ref mut tp) = ty {
+        for seg in &mut tp.path.segments {
+            if let syn::PathArguments::AngleBracketed(ref mut args) = seg.arguments {
+                args.colon2_token = None;
+            }
+        }
+    }
+
+    ty
+}
+
 pub fn strip_generics(mut ty: syn::Type) -> syn::Type {
     match ty {
         syn::Type::Path(ref mut tp) => {
@@ -62,6 +115,43 @@ pub fn strip_generics(mut ty: syn::Type) -> syn::Type {
     ty
 }
 
+/// A name of the type that can be used as an enum variant. The generic arguments are appended
+/// to the name, so that `Packet<u16>` becomes `PacketU16` and `Buffer<64>` becomes `Buffer64`.
+/// The validation rejects the types whose names collide.
+pub fn to_variant_ty(ty: &syn::Type) -> syn::Type {
+    fn append_tokens(name: &mut String, tokens: TokenStream) {
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => append_tokens(name, group.stream()),
+                TokenTree::Ident(ident) => {
+                    let ident = ident.to_string();
+                    let mut chars = ident.chars();
+                    if let Some(first) = chars.next() {
+                        if name.is_empty() {
+                            name.push(first);
+                        } else {
+                            name.extend(first.to_uppercase());
+                        }
+                        name.extend(chars);
+                    }
+                }
+                TokenTree::Literal(lit) => {
+                    name.extend(lit.to_string().chars().filter(|c| c.is_alphanumeric()));
+                }
+                TokenTree::Punct(_) => (),
+            }
+        }
+    }
+
+    let mut name = String::new();
+    append_tokens(&mut name, ty.to_token_stream());
+
+    syn::Type::Path(syn::TypePath {
+        qself: None,
+        path: syn::Ident::new(&name, ty.span()).into(),
+    })
+}
+
 pub fn to_field_name(ty: &syn::Type) -> syn::Ident {
     let ty = strip_generics(ty.clone());
 
@@ -129,16 +219,32 @@ pub fn ty_append(ty: &syn::Type, suffix: &str) -> syn::Type {
     })
 }
 
-pub fn assert_no_generics(ty: &syn::Type) -> syn::Result<()> {
+/// State and event types can have type, lifetime and const generic arguments.
+pub fn assert_supported_ty(ty: &syn::Type) -> syn::Result<()> {
     match ty {
         syn::Type::Path(ref tp) => {
             for seg in &tp.path.segments {
                 match seg.arguments {
                     syn::PathArguments::None => {}
-                    _ => {
+                    syn::PathArguments::AngleBracketed(ref args) => {
+                        for arg in &args.args {
+                            match arg {
+                                syn::GenericArgument::Type(ty) => assert_supported_ty(ty)?,
+                                syn::GenericArgument::Lifetime(_)
+                                | syn::GenericArgument::Const(_) => {}
+                                _ => {
+                                    return Err(syn::Error::new(
+                                        arg.span(),
+                                        "Unsupported generic argument for state or event types!",
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                    syn::PathArguments::Parenthesized(_) => {
                         return Err(syn::Error::new(
                             ty.span(),
-                            "Generics aren't supported for state or event types!",
+                            "Unsupported generic arguments for state or event types!",
                         ));
                     }
                 }
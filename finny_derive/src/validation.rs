@@ -5,9 +5,9 @@ use proc_macro2::Span;
 use syn::spanned::Spanned;
 
 use crate::{
-    parse::{FsmDeclarations, FsmRegion, ValidatedFsm},
+    parse::{FsmDeclarations, FsmRegion, FsmStateKind, ValidatedFsm},
     parse_fsm::FsmCodegenOptions,
-    utils::tokens_to_string,
+    utils::{strip_generics, to_variant_ty, tokens_to_string},
 };
 
 #[derive(Debug)]
@@ -57,6 +57,16 @@ pub fn create_regions(
         }
     }
 
+    // the conditional initial states belong to the region of the unconditional one
+    if let Some(initial_state) = decl.initial_states.first() {
+        for choice in &decl.initial_choices {
+            let state_from = get_or_add_node(&mut nodes, &mut graph, initial_state);
+            let state_to = get_or_add_node(&mut nodes, &mut graph, &choice.state);
+
+            graph.add_edge(state_from, state_to, 0);
+        }
+    }
+
     for (region_id, initial_state) in decl.initial_states.iter().enumerate() {
         let start_node = get_or_add_node(&mut nodes, &mut graph, initial_state);
         let mut dfs = Dfs::new(&graph, start_node);
@@ -127,10 +137,66 @@ pub fn create_regions(
         });
     }
 
+    validate_variant_names(
+        "states",
+        decl.states.values().map(|s| (&s.ty, s.get_variant_ty())),
+    )?;
+    validate_variant_names(
+        "events",
+        decl.events.keys().map(|ty| (ty, to_variant_ty(ty))).chain(
+            decl.states
+                .values()
+                .filter(|s| matches!(s.kind, FsmStateKind::SubMachine(_)))
+                .map(|s| (&s.ty, strip_generics(s.ty.clone()))),
+        ),
+    )?;
+
+    if options.states_storage_enum {
+        if let Some(sub) = decl
+            .states
+            .values()
+            .find(|s| matches!(s.kind, FsmStateKind::SubMachine(_)))
+        {
+            return Err(syn::Error::new(
+                sub.ty.span(),
+                "Submachines aren't supported with the enum states storage.",
+            ));
+        }
+    }
+
     Ok(ValidatedFsm {
         events: decl.events,
         states: decl.states,
+        outputs_ty: decl.outputs_ty,
+        delayed_slots: decl.delayed_slots,
         regions,
         codegen_options: options,
     })
 }
+
+/// The generated enums name their variants after the types, with the generic arguments folded
+/// into the name. Two types that end up with the same name can't share an enum.
+fn validate_variant_names<'a>(
+    kind: &str,
+    types: impl Iterator<Item = (&'a syn::Type, syn::Type)>,
+) -> syn::Result<()> {
+    let mut variants: Vec<_> = types
+        .map(|(ty, variant)| (tokens_to_string(&variant), tokens_to_string(ty), ty))
+        .collect();
+    variants.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+
+    for pair in variants.windows(2) {
+        let ((variant, first, _), (other_variant, second, ty)) = (&pair[0], &pair[1]);
+        if variant == other_variant {
+            return Err(syn::Error::new(
+                ty.span(),
+                format!(
+                    "The {} '{}' and '{}' would share the variant '{}' of the generated enum. Rename one of them, or use a type alias.",
+                    kind, first, second, variant
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+extern crate finny;
+
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+
+use finny::{
+    actor::FsmActor, bundled::derive_more, finny_fsm, FsmCurrentState, FsmError, FsmFactory,
+    FsmResult, FsmTimers,
+};
+
+pub struct Context {
+    log: Sender<&'static str>,
+}
+
+#[derive(Default)]
+pub struct Closed;
+#[derive(Default)]
+pub struct Opening;
+#[derive(Default)]
+pub struct Open;
+
+pub struct OpenDoor;
+pub struct Opened;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Door, Context>) -> BuiltFsm {
+    fsm.initial_state::<Closed>();
+
+    fsm.state::<Closed>()
+        .on_event::<OpenDoor>()
+        .transition_to::<Opening>();
+
+    fsm.state::<Opening>()
+        .on_entry_start_timer(
+            |_ctx, timer| {
+                timer.timeout = Duration::from_millis(20);
+            },
+            |_ctx, _state| Some(Opened.into()),
+        )
+        .with_timer_ty::<OpeningTimer>();
+
+    fsm.state::<Opening>()
+        .on_event::<Opened>()
+        .transition_to::<Open>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.log.send("opened").unwrap();
+        });
+
+    fsm.state::<Open>().on_exit(|_state, ctx| {
+        ctx.log.send("exit open").unwrap();
+    });
+
+    fsm.build()
+}
+
+#[test]
+fn test_actor() -> FsmResult<()> {
+    let (log, received) = mpsc::channel();
+    let actor = FsmActor::spawn(move || Door::new(Context { log }))?;
+
+    actor.send(OpenDoor)?;
+
+    // the timer is triggered by the actor's thread
+    assert_eq!(Ok("opened"), received.recv_timeout(Duration::from_secs(5)));
+    assert_eq!(
+        FsmCurrentState::State(DoorCurrentState::Open),
+        actor.query(|fsm| fsm.get_current_states()[0])?
+    );
+
+    actor.shutdown()?;
+    assert_eq!(Ok("exit open"), received.try_recv());
+
+    Ok(())
+}
+
+#[test]
+fn test_actor_errors() -> FsmResult<()> {
+    let (log, _received) = mpsc::channel();
+    let (errors, received_errors) = mpsc::channel();
+    let actor = FsmActor::spawn_with_errors(
+        move || Door::new(Context { log }),
+        move |e| errors.send(e).unwrap(),
+    )?;
+
+    // the closed door can't be opened without the timer
+    actor.send(Opened)?;
+    assert_eq!(
+        Ok(FsmError::NoTransition),
+        received_errors.recv_timeout(Duration::from_secs(5))
+    );
+
+    actor.shutdown()?;
+    assert!(received_errors.try_recv().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_stop() -> FsmResult<()> {
+    let (log, _received) = mpsc::channel();
+    let mut fsm = Door::new(Context { log })?;
+    fsm.start()?;
+
+    fsm.dispatch(OpenDoor)?;
+    assert!(fsm.timers.time_to_next_timer().is_some());
+
+    fsm.stop()?;
+    assert_eq!([FsmCurrentState::Stopped], fsm.get_current_states());
+    assert_eq!(None, fsm.timers.time_to_next_timer());
+
+    // stopping again is a no-op, the events are rejected until the machine is started again
+    fsm.stop()?;
+    assert!(matches!(
+        fsm.dispatch(OpenDoor),
+        Err(FsmError::NoTransition)
+    ));
+
+    fsm.start()?;
+    assert_eq!(
+        [FsmCurrentState::State(DoorCurrentState::Closed)],
+        fsm.get_current_states()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+extern crate finny;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use finny::{
+    bundled::derive_more, finny_fsm, FsmCurrentState, FsmError, FsmEventQueueSender, FsmFactory,
+    FsmResult,
+};
+
+#[derive(Default)]
+pub struct Context {
+    log: Arc<Mutex<Vec<&'static str>>>,
+}
+
+#[derive(Default)]
+pub struct Idle;
+#[derive(Default)]
+pub struct Loading;
+#[derive(Default)]
+pub struct Ready {
+    value: u32,
+}
+
+pub struct Load;
+pub struct Loaded {
+    value: u32,
+}
+pub struct Refresh;
+pub struct Ping;
+pub struct Stall;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Loader, Context>) -> BuiltFsm {
+    fsm.initial_state::<Idle>();
+
+    fsm.state::<Idle>()
+        .on_event::<Load>()
+        .transition_to::<Loading>()
+        .action_async(|_ev, ctx, _from, _to| {
+            let log = ctx.log.clone();
+            async move {
+                tokio::task::yield_now().await;
+                log.lock().unwrap().push("requested");
+                None
+            }
+        });
+
+    fsm.state::<Loading>().on_entry_async(|_state, ctx| {
+        let log = ctx.log.clone();
+        async move {
+            tokio::task::yield_now().await;
+            log.lock().unwrap().push("loaded");
+            Some(Loaded { value: 42 }.into())
+        }
+    });
+
+    fsm.state::<Loading>()
+        .on_event::<Loaded>()
+        .transition_to::<Ready>()
+        .action(|ev, ctx, _from, to| {
+            to.value = ev.value;
+            ctx.log.lock().unwrap().push("ready");
+        });
+
+    fsm.state::<Ready>()
+        .on_event::<Refresh>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.log.lock().unwrap().push("refresh");
+        });
+
+    fsm.state::<Ready>()
+        .on_event::<Ping>()
+        .internal_transition()
+        .action_async(async |_ev, _ctx, _state| Some(Refresh.into()));
+
+    fsm.state::<Ready>()
+        .on_event::<Stall>()
+        .internal_transition()
+        .action_async(async |_ev, _ctx, _state| {
+            std::future::pending::<()>().await;
+            None
+        });
+
+    fsm.build()
+}
+
+#[tokio::test]
+async fn test_async_actions() -> FsmResult<()> {
+    let ctx = Context::default();
+    let log = ctx.log.clone();
+    let mut fsm = Loader::new(ctx)?;
+
+    fsm.start_async().await?;
+
+    // queued before the step, dispatched only after the completion event of the async entry
+    fsm.queue.enqueue(Refresh)?;
+    fsm.dispatch_async(Load).await?;
+    fsm.dispatch_async(Ping).await?;
+
+    assert_eq!(
+        FsmCurrentState::State(LoaderCurrentState::Ready),
+        fsm.get_current_states()[0]
+    );
+    let ready: &Ready = fsm.get_state();
+    assert_eq!(42, ready.value);
+    assert_eq!(
+        vec!["requested", "loaded", "ready", "refresh", "refresh"],
+        *log.lock().unwrap()
+    );
+    assert!(fsm.tasks.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_async_actions_need_async_dispatch() -> FsmResult<()> {
+    let mut fsm = Loader::new(Context::default())?;
+    fsm.start()?;
+
+    // nothing would await the futures of the sync dispatch
+    assert_eq!(Err(FsmError::NotSupported), fsm.dispatch(Load).map(|_| ()));
+    assert_eq!(
+        FsmCurrentState::State(LoaderCurrentState::Loading),
+        fsm.get_current_states()[0]
+    );
+    assert!(fsm.tasks.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_async_dispatch_dropped() -> FsmResult<()> {
+    let mut fsm = Loader::new(Context::default())?;
+    fsm.start_async().await?;
+    fsm.dispatch_async(Load).await?;
+
+    // the future of the dispatch is dropped while it awaits the stalled action
+    let stalled = tokio::time::timeout(Duration::from_secs(1), fsm.dispatch_async(Stall)).await;
+    assert!(stalled.is_err());
+    assert!(fsm.tasks.is_empty());
+
+    // the sync dispatch rejects the async actions again
+    assert_eq!(Err(FsmError::NotSupported), fsm.dispatch(Ping).map(|_| ()));
+    assert!(fsm.tasks.is_empty());
+
+    Ok(())
+}
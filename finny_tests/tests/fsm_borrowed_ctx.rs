@@ -0,0 +1,70 @@
+extern crate finny;
+
+use finny::{bundled::derive_more, finny_fsm, FsmFactory, FsmResult};
+
+pub struct Connection {
+    sent: Vec<String>,
+}
+
+pub struct Config {
+    greeting: String,
+}
+
+pub struct Ctx<'a> {
+    connection: &'a mut Connection,
+    config: &'a Config,
+}
+
+#[derive(Default)]
+pub struct Closed;
+#[derive(Default)]
+pub struct Open;
+
+#[derive(Clone)]
+pub struct Greet;
+#[derive(Clone)]
+pub struct Close;
+
+#[finny_fsm]
+fn build_fsm<'a>(mut fsm: FsmBuilder<Session<'a>, Ctx<'a>>) -> BuiltFsm {
+    fsm.initial_state::<Closed>();
+
+    fsm.state::<Closed>()
+        .on_event::<Greet>()
+        .transition_to::<Open>()
+        .action(|_ev, ctx, _from, _to| {
+            let greeting = ctx.config.greeting.clone();
+            ctx.connection.sent.push(greeting);
+        });
+
+    fsm.state::<Open>()
+        .on_exit(|_state, ctx| {
+            ctx.connection.sent.push("bye".into());
+        })
+        .on_event::<Close>()
+        .transition_to::<Closed>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_borrowed_ctx() -> FsmResult<()> {
+    let config = Config {
+        greeting: "hello".into(),
+    };
+    let mut connection = Connection { sent: vec![] };
+
+    {
+        let mut fsm = Session::new(Ctx {
+            connection: &mut connection,
+            config: &config,
+        })?;
+        fsm.start()?;
+        fsm.dispatch(Greet)?;
+        fsm.dispatch(Close)?;
+    }
+
+    assert_eq!(vec!["hello", "bye"], connection.sent);
+
+    Ok(())
+}
@@ -0,0 +1,275 @@
+extern crate finny;
+
+use finny::{
+    bundled::derive_more, finny_fsm, inspect::null::InspectNull, FsmCurrentState, FsmError,
+    FsmEventQueueVec, FsmFactory, FsmResult, FsmTimersNull,
+};
+
+#[derive(Default)]
+pub struct BufferContext {
+    buffer: Vec<u8>,
+    flushed: Vec<u8>,
+    flushes: usize,
+}
+
+#[derive(Default)]
+pub struct Waiting;
+#[derive(Default)]
+pub struct Flushing;
+
+#[derive(Debug, Clone)]
+pub struct Push(u8);
+#[derive(Debug, Clone)]
+pub struct Flushed;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<BufferMachine, BufferContext>) -> BuiltFsm {
+    fsm.initial_state::<Waiting>();
+
+    fsm.state::<Waiting>()
+        .on_event::<Push>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            ctx.buffer.push(ev.0);
+        });
+
+    fsm.state::<Waiting>()
+        .when(|ctx, _states| ctx.buffer.len() >= 3)
+        .transition_to::<Flushing>()
+        .action(|ctx, _from, _to| {
+            ctx.flushes += 1;
+        });
+
+    fsm.state::<Flushing>()
+        .on_entry(|_state, ctx| {
+            let mut buffer = std::mem::take(&mut ctx.buffer);
+            ctx.flushed.append(&mut buffer);
+        })
+        .on_event::<Flushed>()
+        .transition_to::<Waiting>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_change_events() -> FsmResult<()> {
+    let mut fsm = BufferMachine::new(BufferContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(Push(1))?;
+    fsm.dispatch(Push(2))?;
+    assert_eq!(
+        FsmCurrentState::State(BufferMachineCurrentState::Waiting),
+        fsm.get_current_states()[0]
+    );
+    assert_eq!(0, fsm.flushes);
+
+    fsm.dispatch(Push(3))?;
+    assert_eq!(
+        FsmCurrentState::State(BufferMachineCurrentState::Flushing),
+        fsm.get_current_states()[0]
+    );
+    assert_eq!(1, fsm.flushes);
+    assert_eq!(vec![1, 2, 3], fsm.flushed);
+    assert!(fsm.buffer.is_empty());
+
+    let res = fsm.dispatch(Push(4));
+    assert_eq!(Err(FsmError::NoTransition), res);
+
+    fsm.dispatch(Flushed)?;
+    assert_eq!(
+        FsmCurrentState::State(BufferMachineCurrentState::Waiting),
+        fsm.get_current_states()[0]
+    );
+    assert_eq!(1, fsm.flushes);
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct ParentContext {
+    limit: usize,
+}
+
+#[derive(Default)]
+pub struct Idle;
+#[derive(Debug, Clone)]
+pub struct Begin;
+
+#[finny_fsm]
+fn build_parent_fsm(mut fsm: FsmBuilder<ParentMachine, ParentContext>) -> BuiltFsm {
+    fsm.initial_state::<Idle>();
+    fsm.state::<Idle>()
+        .on_event::<Begin>()
+        .transition_to::<CounterMachine>();
+
+    fsm.sub_machine::<CounterMachine>()
+        .with_context(|ctx| CounterContext {
+            count: 0,
+            limit: ctx.limit,
+        })
+        .when(|_ctx, states| {
+            let counter: &CounterMachine = states.as_ref();
+            counter.count >= 4
+        })
+        .transition_to::<Idle>();
+
+    fsm.build()
+}
+
+pub struct CounterContext {
+    count: usize,
+    limit: usize,
+}
+
+#[derive(Default)]
+pub struct Counting;
+#[derive(Default)]
+pub struct Done;
+#[derive(Debug, Clone)]
+pub struct Increment;
+
+#[finny_fsm]
+fn build_counter_fsm(mut fsm: FsmBuilder<CounterMachine, CounterContext>) -> BuiltFsm {
+    fsm.initial_state::<Counting>();
+    fsm.state::<Counting>()
+        .on_event::<Increment>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.count += 1;
+        });
+    fsm.state::<Counting>()
+        .when(|ctx, _states| ctx.count >= ctx.limit)
+        .transition_to::<Done>();
+    fsm.state::<Done>()
+        .on_event::<Increment>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.count += 1;
+        });
+    fsm.build()
+}
+
+#[test]
+fn test_change_events_sub() -> FsmResult<()> {
+    let mut fsm = ParentMachine::new_with(
+        ParentContext { limit: 2 },
+        FsmEventQueueVec::new(),
+        InspectNull::new(),
+        FsmTimersNull,
+    )?;
+    fsm.start()?;
+    fsm.dispatch(Begin)?;
+
+    let ev: CounterMachineEvents = Increment.into();
+    fsm.dispatch(ev.clone())?;
+    let sub: &CounterMachine = fsm.get_state();
+    assert_eq!(
+        FsmCurrentState::State(CounterMachineCurrentState::Counting),
+        sub.get_current_states()[0]
+    );
+
+    fsm.dispatch(ev.clone())?;
+    let sub: &CounterMachine = fsm.get_state();
+    assert_eq!(
+        FsmCurrentState::State(CounterMachineCurrentState::Done),
+        sub.get_current_states()[0]
+    );
+
+    fsm.dispatch(ev.clone())?;
+    assert_eq!(
+        FsmCurrentState::State(ParentMachineCurrentState::CounterMachine),
+        fsm.get_current_states()[0]
+    );
+
+    fsm.dispatch(ev)?;
+    assert_eq!(
+        FsmCurrentState::State(ParentMachineCurrentState::Idle),
+        fsm.get_current_states()[0]
+    );
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct BlinkContext {
+    on: bool,
+    blinks: usize,
+}
+
+#[derive(Default)]
+pub struct Dark;
+#[derive(Default)]
+pub struct Lit;
+
+#[derive(Debug, Clone)]
+pub struct Switch(bool);
+
+#[finny_fsm]
+fn build_blink_fsm(mut fsm: FsmBuilder<BlinkMachine, BlinkContext>) -> BuiltFsm {
+    fsm.initial_state::<Dark>();
+
+    fsm.state::<Dark>()
+        .on_event::<Switch>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            ctx.on = ev.0;
+        });
+    fsm.state::<Dark>()
+        .when(|ctx, _states| ctx.on)
+        .transition_to::<Lit>()
+        .action(|ctx, _from, _to| {
+            ctx.blinks += 1;
+        });
+
+    // the condition stays true in the target state
+    fsm.state::<Lit>()
+        .on_event::<Switch>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            ctx.on = ev.0;
+        });
+    fsm.state::<Lit>()
+        .when(|ctx, _states| ctx.on)
+        .transition_to::<Dark>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_change_events_once_per_step() -> FsmResult<()> {
+    let mut fsm = BlinkMachine::new(BlinkContext {
+        on: true,
+        blinks: 0,
+    })?;
+
+    // evaluated after the start as well
+    fsm.start()?;
+    assert_eq!(
+        FsmCurrentState::State(BlinkMachineCurrentState::Lit),
+        fsm.get_current_states()[0]
+    );
+    assert_eq!(1, fsm.blinks);
+
+    // fires once per step while the condition holds
+    fsm.dispatch(Switch(true))?;
+    assert_eq!(
+        FsmCurrentState::State(BlinkMachineCurrentState::Dark),
+        fsm.get_current_states()[0]
+    );
+    fsm.dispatch(Switch(true))?;
+    assert_eq!(
+        FsmCurrentState::State(BlinkMachineCurrentState::Lit),
+        fsm.get_current_states()[0]
+    );
+    assert_eq!(2, fsm.blinks);
+
+    fsm.dispatch(Switch(false))?;
+    assert_eq!(
+        FsmCurrentState::State(BlinkMachineCurrentState::Lit),
+        fsm.get_current_states()[0]
+    );
+    assert_eq!(2, fsm.blinks);
+
+    Ok(())
+}
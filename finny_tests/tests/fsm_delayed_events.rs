@@ -0,0 +1,220 @@
+extern crate finny;
+
+use std::time::Duration;
+
+use finny::{
+    bundled::derive_more, finny_fsm, inspect::null::InspectNull, FsmCurrentState, FsmError,
+    FsmEventQueueArray, FsmEventQueueVec, FsmFactory, FsmResult, FsmTimers, TimerSettings,
+};
+
+const DELAYED_SLOTS: usize = 4;
+
+#[derive(Default)]
+pub struct Context {
+    log: Vec<&'static str>,
+}
+
+#[derive(Default)]
+pub struct Idle;
+
+pub struct Retry;
+pub struct Burst;
+pub struct Connect;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Client, Context>) -> BuiltFsm {
+    fsm.initial_state::<Idle>();
+    fsm.delayed_events::<DELAYED_SLOTS>();
+
+    fsm.state::<Idle>()
+        .on_event::<Retry>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.log.push("retry");
+        });
+
+    fsm.state::<Idle>()
+        .on_event::<Burst>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            for _ in 0..DELAYED_SLOTS {
+                assert!(ctx.enqueue_after(Duration::from_secs(1), Retry).is_ok());
+            }
+            match ctx.enqueue_after(Duration::from_secs(1), Retry) {
+                Err((FsmError::QueueOverCapacity, ClientEvents::Retry(_))) => {
+                    ctx.log.push("full");
+                }
+                _ => panic!("Expected the slots to be taken."),
+            }
+        });
+
+    fsm.state::<Idle>()
+        .on_event::<Connect>()
+        .transition_to::<Connection>();
+
+    fsm.sub_machine::<Connection>()
+        .with_context(|_ctx| ConnectionContext::default());
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct ConnectionContext {
+    attempts: usize,
+}
+
+#[derive(Default)]
+pub struct Connecting;
+
+pub struct Timeout;
+
+#[finny_fsm]
+fn build_sub_fsm(mut fsm: FsmBuilder<Connection, ConnectionContext>) -> BuiltFsm {
+    fsm.initial_state::<Connecting>();
+
+    fsm.state::<Connecting>()
+        .on_entry(|_state, ctx| {
+            assert!(ctx
+                .enqueue_after(Duration::from_millis(500), Timeout)
+                .is_ok());
+        })
+        .on_event::<Timeout>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.attempts += 1;
+        });
+
+    fsm.build()
+}
+
+/// Timers that are triggered by the test.
+#[derive(Default)]
+pub struct ManualTimers {
+    created: Vec<(ClientTimers, Duration)>,
+    fail: bool,
+}
+
+impl ManualTimers {
+    fn trigger_all(&mut self) {
+        self.created.reverse();
+    }
+}
+
+impl FsmTimers<Client> for ManualTimers {
+    fn create(&mut self, id: ClientTimers, settings: &TimerSettings) -> FsmResult<()> {
+        assert!(!settings.renew);
+        if self.fail {
+            return Err(FsmError::NotSupported);
+        }
+        self.created.push((id, settings.timeout));
+        Ok(())
+    }
+
+    fn cancel(&mut self, _id: ClientTimers) -> FsmResult<()> {
+        Ok(())
+    }
+
+    fn get_triggered_timer(&mut self) -> Option<ClientTimers> {
+        self.created.pop().map(|(id, _)| id)
+    }
+}
+
+#[test]
+fn test_enqueue_after() -> FsmResult<()> {
+    let mut fsm = Client::new_with(
+        Context::default(),
+        FsmEventQueueVec::new(),
+        InspectNull::new(),
+        ManualTimers::default(),
+    )?;
+    fsm.start()?;
+
+    fsm.dispatch(Burst)?;
+    assert_eq!(vec!["full"], fsm.log);
+    assert_eq!(DELAYED_SLOTS, fsm.delayed.len());
+    assert_eq!(DELAYED_SLOTS, fsm.timers.created.len());
+    assert!(fsm
+        .timers
+        .created
+        .iter()
+        .all(|(_, timeout)| *timeout == Duration::from_secs(1)));
+
+    fsm.timers.trigger_all();
+    fsm.dispatch_timer_events()?;
+    assert_eq!(DELAYED_SLOTS + 1, fsm.log.len());
+    assert!(fsm.delayed.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_enqueue_after_sub_machine() -> FsmResult<()> {
+    let mut fsm = Client::new_with(
+        Context::default(),
+        FsmEventQueueVec::new(),
+        InspectNull::new(),
+        ManualTimers::default(),
+    )?;
+    fsm.start()?;
+
+    fsm.dispatch(Connect)?;
+    assert_eq!(1, fsm.delayed.len());
+    assert_eq!(Duration::from_millis(500), fsm.timers.created[0].1);
+
+    fsm.dispatch_timer_events()?;
+    let connection: &Connection = fsm.get_state();
+    assert_eq!(1, connection.attempts);
+
+    Ok(())
+}
+
+#[test]
+fn test_enqueue_after_timer_failed() -> FsmResult<()> {
+    let mut fsm = Client::new_with(
+        Context::default(),
+        FsmEventQueueVec::new(),
+        InspectNull::new(),
+        ManualTimers {
+            fail: true,
+            ..Default::default()
+        },
+    )?;
+    fsm.start()?;
+
+    // the transition is kept, only the delayed event is lost
+    let outcome = fsm.dispatch(Connect)?;
+    assert_eq!(1, outcome.delayed_events_dropped);
+    assert_eq!(
+        FsmCurrentState::State(ClientCurrentState::Connection),
+        fsm.get_current_states()[0]
+    );
+    assert!(fsm.delayed.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_enqueue_after_queue_full() -> FsmResult<()> {
+    let mut fsm = Client::new_with(
+        Context::default(),
+        FsmEventQueueArray::<_, 1>::new(),
+        InspectNull::new(),
+        ManualTimers::default(),
+    )?;
+    fsm.start()?;
+
+    fsm.dispatch(Burst)?;
+    fsm.timers.trigger_all();
+
+    // only the first delayed event fits into the queue, the rest are returned
+    let outcome = fsm.dispatch_timer_events()?;
+    assert_eq!(vec!["full", "retry"], fsm.log);
+    assert_eq!(DELAYED_SLOTS - 1, outcome.rejected_events.len());
+    assert!(outcome
+        .rejected_events
+        .iter()
+        .all(|ev| matches!(ev, ClientEvents::Retry(_))));
+    assert!(fsm.delayed.is_empty());
+
+    Ok(())
+}
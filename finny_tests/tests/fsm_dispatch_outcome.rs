@@ -0,0 +1,105 @@
+extern crate finny;
+
+use finny::{
+    bundled::derive_more, finny_fsm, FsmCurrentState, FsmError, FsmFactory, FsmResult,
+    FsmTransitionKind,
+};
+
+#[derive(Default)]
+pub struct Context {
+    counter: usize,
+}
+
+#[derive(Default)]
+pub struct Off;
+#[derive(Default)]
+pub struct On;
+#[derive(Default)]
+pub struct Watching;
+
+pub struct Toggle;
+pub struct Tick;
+pub struct Burst(usize);
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Lamp, Context>) -> BuiltFsm {
+    fsm.initial_states::<(Off, Watching)>();
+
+    // region 1
+
+    fsm.state::<Off>()
+        .on_event::<Toggle>()
+        .transition_to::<On>();
+
+    fsm.state::<On>()
+        .on_event::<Toggle>()
+        .transition_to::<Off>();
+
+    fsm.state::<On>()
+        .on_event::<Tick>()
+        .internal_transition()
+        .guard(|_ev, ctx, _| ctx.counter < 2)
+        .action(|_ev, ctx, _state| {
+            ctx.counter += 1;
+        });
+
+    // region 2
+
+    fsm.state::<Watching>()
+        .on_event::<Burst>()
+        .self_transition()
+        .action(|ev, ctx, _state| {
+            for _ in 0..ev.0 {
+                ctx.queue.enqueue(Tick).unwrap();
+            }
+        });
+
+    fsm.build()
+}
+
+#[test]
+fn test_dispatch_outcome() -> FsmResult<()> {
+    let mut fsm = Lamp::new(Context::default())?;
+    fsm.start()?;
+
+    let outcome = fsm.dispatch(Toggle)?;
+    let transition = outcome.transition(0).unwrap();
+    assert_eq!(FsmTransitionKind::State, transition.kind);
+    assert!(transition.transition.contains("LampTransition"));
+    assert_eq!(None, outcome.transition(1));
+    assert_eq!(
+        FsmCurrentState::State(LampCurrentState::Off),
+        outcome.states_before[0]
+    );
+    assert_eq!(
+        FsmCurrentState::State(LampCurrentState::On),
+        outcome.states_after[0]
+    );
+    assert!(outcome.states_changed());
+    assert_eq!(0, outcome.queued_events);
+
+    let outcome = fsm.dispatch(Tick)?;
+    assert_eq!(
+        Some(FsmTransitionKind::Internal),
+        outcome.transition(0).map(|t| t.kind)
+    );
+    assert!(!outcome.states_changed());
+
+    // the guard rejects the second tick
+    let outcome = fsm.dispatch(Burst(2))?;
+    assert_eq!(None, outcome.transition(0));
+    assert_eq!(
+        Some(FsmTransitionKind::SelfTransition),
+        outcome.transition(1).map(|t| t.kind)
+    );
+    assert_eq!(2, outcome.queued_events);
+    assert_eq!(1, outcome.queue_errors.len());
+    assert_eq!(1, outcome.queue_errors[0].index);
+    assert_eq!(FsmError::NoTransition, outcome.queue_errors[0].error);
+    assert_eq!(2, fsm.counter);
+
+    let ret = fsm.dispatch(Tick);
+    assert_eq!(Err(FsmError::NoTransition), ret);
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+extern crate finny;
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use finny::{
+    bundled::derive_more, finny_fsm, inspect::null::InspectNull, FsmEventQueueVec, FsmFactory,
+    FsmResult, FsmTimersNull,
+};
+
+/// Not `Clone`.
+pub struct Reply(Option<Sender<usize>>);
+
+pub struct Query {
+    reply: Reply,
+}
+pub struct Write {
+    buffer: Vec<u8>,
+}
+pub struct Open;
+
+#[derive(Default)]
+pub struct Context {
+    written: usize,
+}
+
+#[derive(Default)]
+pub struct Closed;
+#[derive(Default)]
+pub struct Idle;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, Context>) -> BuiltFsm {
+    fsm.initial_states::<(Closed, Idle)>();
+
+    // region 1
+
+    fsm.state::<Closed>()
+        .on_event::<Open>()
+        .transition_to::<WriterMachine>();
+
+    fsm.sub_machine::<WriterMachine>();
+
+    // region 2
+
+    fsm.state::<Idle>()
+        .on_event::<Query>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            if let Some(ref sender) = ev.reply.0 {
+                sender.send(ctx.written).unwrap();
+            }
+        });
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct WriterContext {
+    written: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct Writing;
+
+#[finny_fsm]
+fn build_writer_fsm(mut fsm: FsmBuilder<WriterMachine, WriterContext>) -> BuiltFsm {
+    fsm.initial_state::<Writing>();
+
+    fsm.state::<Writing>()
+        .on_event::<Write>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            ctx.written.extend_from_slice(&ev.buffer);
+        });
+
+    fsm.state::<Writing>()
+        .on_event::<Query>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            if let Some(ref sender) = ev.reply.0 {
+                sender.send(ctx.written.len()).unwrap();
+            }
+        });
+
+    fsm.build()
+}
+
+fn query() -> (Query, Receiver<usize>) {
+    let (sender, receiver) = channel();
+    let query = Query {
+        reply: Reply(Some(sender)),
+    };
+    (query, receiver)
+}
+
+#[test]
+fn test_events_no_clone() -> FsmResult<()> {
+    let mut fsm = StateMachine::new_with(
+        Context { written: 7 },
+        FsmEventQueueVec::new(),
+        InspectNull::new(),
+        FsmTimersNull,
+    )?;
+    fsm.start()?;
+
+    let (q, receiver) = query();
+    fsm.dispatch(q)?;
+    assert_eq!(Ok(7), receiver.try_recv());
+
+    fsm.dispatch(Open)?;
+
+    let ev: WriterMachineEvents = Write {
+        buffer: vec![1, 2, 3],
+    }
+    .into();
+    fsm.dispatch(ev)?;
+
+    let (q, receiver) = query();
+    let ev: WriterMachineEvents = q.into();
+    fsm.dispatch(ev)?;
+    assert_eq!(Ok(3), receiver.try_recv());
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+extern crate finny;
+
+use finny::{bundled::derive_more, finny_fsm, FsmCurrentState, FsmFactory, FsmResult};
+
+#[derive(Default)]
+pub struct Context {
+    packets: Vec<u32>,
+}
+
+#[derive(Default)]
+pub struct Idle;
+pub struct Buffer<const N: usize> {
+    data: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Default for Buffer<N> {
+    fn default() -> Self {
+        Self {
+            data: [0; N],
+            len: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Packet<T> {
+    payload: T,
+}
+#[derive(Debug, Clone)]
+pub struct Grow;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, Context>) -> BuiltFsm {
+    fsm.initial_state::<Idle>();
+
+    fsm.state::<Idle>()
+        .on_event::<Packet<u16>>()
+        .transition_to::<Buffer<2>>()
+        .action(|ev, ctx, _from, to| {
+            ctx.packets.push(ev.payload as u32);
+            to.data[0] = ev.payload as u8;
+            to.len = 1;
+        });
+
+    fsm.state::<Idle>()
+        .on_event::<Packet<u32>>()
+        .transition_to::<Buffer<8>>()
+        .action(|ev, ctx, _from, to| {
+            ctx.packets.push(ev.payload);
+            to.len = 4;
+        });
+
+    fsm.state::<Buffer<2>>()
+        .on_event::<Grow>()
+        .transition_to::<Buffer<8>>()
+        .action(|_ev, _ctx, from, to| {
+            to.data[..from.len].copy_from_slice(&from.data[..from.len]);
+            to.len = from.len;
+        });
+
+    fsm.state::<Buffer<8>>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_generic_types() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(Context::default())?;
+    fsm.start()?;
+
+    let ev: StateMachineEvents = Packet { payload: 7u16 }.into();
+    assert_eq!("Packet<u16>", ev.as_ref());
+    fsm.dispatch(ev)?;
+    assert_eq!(
+        FsmCurrentState::State(StateMachineCurrentState::Buffer2),
+        fsm.get_current_states()[0]
+    );
+    let state: &Buffer<2> = fsm.get_state();
+    assert_eq!(1, state.len);
+
+    fsm.dispatch(Grow)?;
+    assert_eq!(
+        FsmCurrentState::State(StateMachineCurrentState::Buffer8),
+        fsm.get_current_states()[0]
+    );
+    let state: &Buffer<8> = fsm.get_state();
+    assert_eq!(&[7], &state.data[..state.len]);
+
+    let mut fsm = StateMachine::new(Context::default())?;
+    fsm.start()?;
+    fsm.dispatch(Packet { payload: 70_000u32 })?;
+    assert_eq!(
+        FsmCurrentState::State(StateMachineCurrentState::Buffer8),
+        fsm.get_current_states()[0]
+    );
+    assert_eq!(vec![70_000], fsm.packets);
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+extern crate finny;
+
+use finny::{bundled::derive_more, finny_fsm, FsmCurrentState, FsmFactory, FsmResult};
+
+#[derive(Default)]
+pub struct MainContext {
+    saved: bool,
+    token: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct Idle;
+#[derive(Default)]
+pub struct Restored;
+
+#[derive(Debug, Clone)]
+pub struct Connect {
+    token: Option<usize>,
+}
+#[derive(Debug, Clone)]
+pub struct Disconnect;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, MainContext>) -> BuiltFsm {
+    fsm.initial_choice()
+        .when(|ctx| ctx.saved, Restored)
+        .otherwise::<Idle>();
+
+    fsm.state::<Restored>()
+        .on_event::<Connect>()
+        .transition_to::<Session>();
+
+    fsm.state::<Idle>()
+        .on_event::<Connect>()
+        .transition_to::<Session>()
+        .action(|ev, ctx, _from, _to| {
+            ctx.token = ev.token;
+        });
+
+    fsm.sub_machine::<Session>()
+        .with_context(|_ctx| SessionContext::default())
+        .on_entry(|session, ctx| {
+            session.token = ctx.token;
+        })
+        .on_event::<Disconnect>()
+        .transition_to::<Idle>();
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct SessionContext {
+    token: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct Handshake;
+#[derive(Default)]
+pub struct Resumed;
+
+#[finny_fsm]
+fn build_session_fsm(mut fsm: FsmBuilder<Session, SessionContext>) -> BuiltFsm {
+    fsm.initial_choice()
+        .when(|ctx| ctx.token.is_some(), Resumed)
+        .otherwise::<Handshake>();
+
+    fsm.state::<Handshake>();
+    fsm.state::<Resumed>();
+    fsm.build()
+}
+
+#[test]
+fn test_initial_choice() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(MainContext::default())?;
+    fsm.start()?;
+    assert_eq!(
+        FsmCurrentState::State(StateMachineCurrentState::Idle),
+        fsm.get_current_states()[0]
+    );
+
+    let mut fsm = StateMachine::new(MainContext {
+        saved: true,
+        token: None,
+    })?;
+    fsm.start()?;
+    assert_eq!(
+        FsmCurrentState::State(StateMachineCurrentState::Restored),
+        fsm.get_current_states()[0]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_initial_choice_sub_reentry() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(MainContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(Connect { token: None })?;
+    let session: &Session = fsm.get_state();
+    assert_eq!(
+        FsmCurrentState::State(SessionCurrentState::Handshake),
+        session.get_current_states()[0]
+    );
+
+    fsm.dispatch(Disconnect)?;
+    assert_eq!(
+        FsmCurrentState::State(StateMachineCurrentState::Idle),
+        fsm.get_current_states()[0]
+    );
+
+    fsm.dispatch(Connect { token: Some(42) })?;
+    let session: &Session = fsm.get_state();
+    assert_eq!(
+        FsmCurrentState::State(SessionCurrentState::Resumed),
+        session.get_current_states()[0]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+extern crate finny;
+
+use finny::{
+    bundled::derive_more, finny_fsm, inspect::null::InspectNull, FsmEventQueue,
+    FsmEventQueueSender, FsmEventQueueVec, FsmFactory, FsmOutputsNull, FsmResult, FsmTimersNull,
+};
+
+#[derive(Default)]
+pub struct Context {
+    log: Vec<&'static str>,
+}
+
+#[derive(Default)]
+pub struct Running;
+
+pub struct Request(&'static str);
+pub struct FollowUp(&'static str);
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Server, Context>) -> BuiltFsm {
+    fsm.initial_state::<Running>();
+
+    fsm.state::<Running>()
+        .on_event::<Request>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            ctx.log.push(ev.0);
+            ctx.queue.enqueue(FollowUp(ev.0)).unwrap();
+        });
+
+    fsm.state::<Running>()
+        .on_event::<FollowUp>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            ctx.log.push(if ev.0 == "a" { "a done" } else { "b done" });
+        });
+
+    fsm.build()
+}
+
+#[test]
+fn test_internal_queue() -> FsmResult<()> {
+    let mut fsm = Server::new_with_internal_queue(
+        Context::default(),
+        FsmEventQueueVec::new(),
+        FsmEventQueueVec::new(),
+        InspectNull::new(),
+        FsmTimersNull,
+        FsmOutputsNull,
+    )?;
+    fsm.start()?;
+
+    // the external input is already waiting
+    fsm.queue.enqueue(Request("b"))?;
+
+    let outcome = fsm.dispatch(Request("a"))?;
+    assert_eq!(3, outcome.queued_events);
+    assert_eq!(vec!["a", "a done", "b", "b done"], fsm.log);
+    assert_eq!(0, fsm.queue.len());
+    assert_eq!(0, fsm.internal_queue.len());
+
+    Ok(())
+}
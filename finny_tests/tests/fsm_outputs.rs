@@ -0,0 +1,157 @@
+extern crate finny;
+
+use finny::{
+    bundled::derive_more, finny_fsm, inspect::null::InspectNull, FsmEventQueueVec, FsmFactory,
+    FsmOutputsVec, FsmResult, FsmTimersNull,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Dispense(u32),
+    Refund(u32),
+    Display(&'static str),
+}
+
+#[derive(Default)]
+pub struct Context {
+    credit: u32,
+}
+
+#[derive(Default)]
+pub struct Idle;
+#[derive(Default)]
+pub struct Paid;
+
+pub struct Coin(u32);
+pub struct Select {
+    item: u32,
+    price: u32,
+}
+pub struct Cancel;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<VendingMachine, Context>) -> BuiltFsm {
+    fsm.outputs::<Command>();
+    fsm.initial_state::<Idle>();
+
+    fsm.state::<Idle>()
+        .on_event::<Coin>()
+        .transition_to::<Paid>()
+        .action(|ev, ctx, _from, _to| {
+            ctx.credit += ev.0;
+            ctx.emit(Command::Display("paid")).unwrap();
+        });
+
+    fsm.state::<Paid>()
+        .on_event::<Coin>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            ctx.credit += ev.0;
+        });
+
+    fsm.state::<Paid>()
+        .on_event::<Select>()
+        .transition_to::<Idle>()
+        .guard(|ev, ctx, _| ev.price <= ctx.credit)
+        .action(|ev, ctx, _from, _to| {
+            let change = ctx.credit - ev.price;
+            ctx.credit = 0;
+            ctx.emit(Command::Dispense(ev.item)).unwrap();
+            if change > 0 {
+                ctx.emit(Command::Refund(change)).unwrap();
+            }
+        });
+
+    fsm.state::<Paid>()
+        .on_event::<Cancel>()
+        .transition_to::<Idle>()
+        .action(|_ev, ctx, _from, _to| {
+            let credit = ctx.credit;
+            ctx.credit = 0;
+            ctx.emit(Command::Refund(credit)).unwrap();
+        });
+
+    fsm.build()
+}
+
+#[test]
+fn test_outputs() -> FsmResult<()> {
+    let mut fsm = VendingMachine::new(Context::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(Coin(2))?;
+    fsm.dispatch(Coin(2))?;
+    assert_eq!(
+        vec![Command::Display("paid")],
+        fsm.outputs.drain().collect::<Vec<_>>()
+    );
+
+    fsm.dispatch(Select { item: 7, price: 3 })?;
+    assert_eq!(
+        vec![Command::Dispense(7), Command::Refund(1)],
+        fsm.outputs.drain().collect::<Vec<_>>()
+    );
+    assert!(fsm.outputs.is_empty());
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, derive_more::From)]
+pub enum ParentCommand {
+    Vending(Command),
+    Alarm,
+}
+
+#[derive(Default)]
+pub struct Armed;
+pub struct Tamper;
+pub struct Start;
+
+#[finny_fsm]
+fn build_parent_fsm(mut fsm: FsmBuilder<Kiosk, ()>) -> BuiltFsm {
+    fsm.outputs::<ParentCommand>();
+    fsm.initial_state::<Armed>();
+
+    fsm.state::<Armed>()
+        .on_event::<Start>()
+        .transition_to::<VendingMachine>();
+
+    fsm.sub_machine::<VendingMachine>()
+        .on_event::<Tamper>()
+        .transition_to::<Armed>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.emit(ParentCommand::Alarm).unwrap();
+        });
+
+    fsm.build()
+}
+
+#[test]
+fn test_outputs_sub() -> FsmResult<()> {
+    let mut fsm = Kiosk::new_with_outputs(
+        (),
+        FsmEventQueueVec::new(),
+        InspectNull::new(),
+        FsmTimersNull,
+        FsmOutputsVec::new(),
+    )?;
+    fsm.start()?;
+    fsm.dispatch(Start)?;
+
+    let ev: VendingMachineEvents = Coin(1).into();
+    fsm.dispatch(ev)?;
+    let ev: VendingMachineEvents = Cancel.into();
+    fsm.dispatch(ev)?;
+    fsm.dispatch(Tamper)?;
+
+    assert_eq!(
+        &[
+            ParentCommand::Vending(Command::Display("paid")),
+            ParentCommand::Vending(Command::Refund(1)),
+            ParentCommand::Alarm
+        ],
+        fsm.outputs.as_slice()
+    );
+
+    Ok(())
+}
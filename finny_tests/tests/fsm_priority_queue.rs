@@ -0,0 +1,106 @@
+extern crate finny;
+
+use finny::{
+    bundled::derive_more, finny_fsm, inspect::null::InspectNull, FsmEventPriority,
+    FsmEventQueuePriority, FsmEventQueuePriorityArray, FsmEventQueueSender, FsmFactory, FsmResult,
+    FsmTimersNull,
+};
+
+#[derive(Default)]
+pub struct Context {
+    log: Vec<&'static str>,
+}
+
+#[derive(Default)]
+pub struct Moving;
+#[derive(Default)]
+pub struct Stopped;
+
+pub struct Telemetry;
+pub struct Cancel;
+pub struct EmergencyStop;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Robot, Context>) -> BuiltFsm {
+    fsm.initial_state::<Moving>();
+
+    fsm.state::<Moving>()
+        .on_event::<Telemetry>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.log.push("telemetry");
+        });
+
+    #[priority(5)]
+    fsm.state::<Moving>()
+        .on_event::<Cancel>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.log.push("cancel");
+        });
+
+    #[priority(10)]
+    fsm.state::<Moving>()
+        .on_event::<EmergencyStop>()
+        .transition_to::<Stopped>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.log.push("stop");
+        });
+
+    fsm.state::<Stopped>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_priority_attributes() {
+    assert_eq!(0, RobotEvents::from(Telemetry).priority());
+    assert_eq!(5, RobotEvents::from(Cancel).priority());
+    assert_eq!(10, RobotEvents::from(EmergencyStop).priority());
+}
+
+#[test]
+fn test_priority_queue() -> FsmResult<()> {
+    let mut fsm = Robot::new_with(
+        Context::default(),
+        FsmEventQueuePriority::new(),
+        InspectNull::new(),
+        FsmTimersNull,
+    )?;
+    fsm.start()?;
+
+    fsm.queue.enqueue(Telemetry)?;
+    fsm.queue.enqueue(Telemetry)?;
+    fsm.queue.enqueue(Cancel)?;
+    fsm.queue.enqueue(Telemetry)?;
+    fsm.queue.enqueue(EmergencyStop)?;
+    fsm.dispatch_queue()?;
+
+    assert_eq!(vec!["stop"], fsm.log);
+
+    Ok(())
+}
+
+#[test]
+fn test_priority_queue_fn() -> FsmResult<()> {
+    let mut fsm = Robot::new_with(
+        Context::default(),
+        FsmEventQueuePriorityArray::<_, 8>::with_priority(|ev| match ev {
+            RobotEvents::Telemetry(_) => 1,
+            _ => 0,
+        }),
+        InspectNull::new(),
+        FsmTimersNull,
+    )?;
+    fsm.start()?;
+
+    fsm.queue.enqueue(Cancel)?;
+    fsm.queue.enqueue(Telemetry)?;
+    fsm.queue.enqueue(Cancel)?;
+    fsm.queue.enqueue(Telemetry)?;
+    fsm.dispatch_queue()?;
+
+    assert_eq!(vec!["telemetry", "telemetry", "cancel", "cancel"], fsm.log);
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+extern crate finny;
+
+use finny::{
+    bundled::derive_more, finny_fsm, FsmEventQueue, FsmEventQueueSender, FsmFactory, FsmResult,
+};
+
+#[derive(Default)]
+pub struct Context {
+    sent: Vec<usize>,
+}
+
+#[derive(Default)]
+pub struct Connected;
+#[derive(Default)]
+pub struct Disconnected;
+
+pub struct SendPacket(usize);
+pub struct Disconnect;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Link, Context>) -> BuiltFsm {
+    fsm.initial_state::<Connected>();
+
+    fsm.state::<Connected>()
+        .on_event::<SendPacket>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            ctx.sent.push(ev.0);
+        });
+
+    fsm.state::<Connected>()
+        .on_event::<Disconnect>()
+        .transition_to::<Disconnected>();
+
+    fsm.state::<Disconnected>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_queue_management() -> FsmResult<()> {
+    let mut fsm = Link::new(Context::default())?;
+    fsm.start()?;
+
+    for n in 0..3 {
+        fsm.queue.enqueue(SendPacket(n))?;
+    }
+    fsm.queue.enqueue(Disconnect)?;
+    fsm.internal_queue.enqueue(SendPacket(10))?;
+
+    // the internal queue goes first
+    assert!(matches!(
+        fsm.peek_queue(),
+        Some(LinkEvents::SendPacket(SendPacket(10)))
+    ));
+    let backlog: Vec<_> = fsm.iter_queue().map(|ev| ev.event_name()).collect();
+    assert_eq!(
+        vec![
+            "SendPacket",
+            "SendPacket",
+            "SendPacket",
+            "SendPacket",
+            "Disconnect"
+        ],
+        backlog
+    );
+
+    // purge the packets that would be sent into a closed link
+    fsm.retain_queue(|ev| !matches!(ev, LinkEvents::SendPacket(_)))?;
+    assert_eq!(0, fsm.internal_queue.len());
+    assert_eq!(1, fsm.queue.len());
+
+    fsm.dispatch_queue()?;
+    assert!(fsm.sent.is_empty());
+
+    fsm.queue.enqueue(SendPacket(1))?;
+    fsm.clear_queue();
+    assert!(fsm.iter_queue().next().is_none());
+
+    Ok(())
+}
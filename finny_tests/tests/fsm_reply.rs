@@ -0,0 +1,89 @@
+extern crate finny;
+
+use finny::{
+    bundled::derive_more, finny_fsm, FsmError, FsmEventQueue, FsmEventQueueSender, FsmFactory,
+    FsmResult,
+};
+
+#[derive(Default)]
+pub struct Context {
+    next_id: u64,
+    log: Vec<u64>,
+}
+
+#[derive(Default)]
+pub struct Accepting;
+#[derive(Default)]
+pub struct Full;
+
+pub struct Register;
+pub struct Audit(u64);
+pub struct Ping;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Registry, Context>) -> BuiltFsm {
+    fsm.initial_state::<Accepting>();
+
+    fsm.state::<Accepting>()
+        .on_event::<Register>()
+        .internal_transition()
+        .guard(|_ev, ctx, _| ctx.next_id < 2)
+        .action(|_ev, ctx, _state| {
+            let id = ctx.next_id;
+            ctx.next_id += 1;
+            ctx.reply::<Result<u64, &'static str>>(Ok(id));
+            // the audit event can't overwrite the reply
+            ctx.queue.enqueue(Audit(id)).unwrap();
+        });
+
+    fsm.state::<Accepting>()
+        .on_event::<Register>()
+        .transition_to::<Full>()
+        .guard(|_ev, ctx, _| ctx.next_id >= 2)
+        .action(|_ev, ctx, _from, _to| {
+            ctx.reply::<Result<u64, &'static str>>(Err("full"));
+        });
+
+    fsm.state::<Accepting>()
+        .on_event::<Audit>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            ctx.log.push(ev.0);
+            assert!(!ctx.reply::<Result<u64, &'static str>>(Ok(100)));
+        });
+
+    fsm.state::<Accepting>()
+        .on_event::<Ping>()
+        .internal_transition();
+
+    fsm.state::<Full>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_reply() -> FsmResult<()> {
+    let mut fsm = Registry::new(Context::default())?;
+    fsm.start()?;
+
+    let reply: FsmResult<String> = fsm.dispatch_with_reply(Register);
+    assert_eq!(Err(FsmError::NoReply), reply);
+
+    let reply: Result<u64, &str> = fsm.dispatch_with_reply(Register)?;
+    assert_eq!(Ok(1), reply);
+    assert_eq!(vec![0, 1], fsm.log);
+
+    let reply: FsmResult<u64> = fsm.dispatch_with_reply(Ping);
+    assert_eq!(Err(FsmError::NoReply), reply);
+
+    let reply: Result<u64, &str> = fsm.dispatch_with_reply(Register)?;
+    assert_eq!(Err("full"), reply);
+
+    // the queued events wait for the next dispatch, same as with `dispatch`
+    fsm.queue.enqueue(Ping)?;
+    let reply: FsmResult<Result<u64, &str>> = fsm.dispatch_with_reply(Register);
+    assert_eq!(Err(FsmError::NoTransition), reply);
+    assert_eq!(1, fsm.queue.len());
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+extern crate finny;
+
+use std::{thread, time::Duration};
+
+use finny::{
+    bundled::derive_more, finny_fsm, inspect::null::InspectNull, FsmCurrentState,
+    FsmEventQueueSender, FsmEventQueueVecShared, FsmFactory, FsmResult, FsmTimersNull,
+};
+
+#[derive(Default)]
+pub struct Context {
+    total: usize,
+}
+
+#[derive(Default)]
+pub struct Counting;
+#[derive(Default)]
+pub struct Done;
+
+pub struct Add(usize);
+pub struct Finish;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Counter, Context>) -> BuiltFsm {
+    fsm.initial_state::<Counting>();
+
+    fsm.state::<Counting>()
+        .on_event::<Add>()
+        .internal_transition()
+        .action(|ev, ctx, _state| {
+            ctx.total += ev.0;
+        });
+
+    fsm.state::<Counting>()
+        .on_event::<Finish>()
+        .transition_to::<Done>();
+
+    fsm.state::<Done>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_sender() -> FsmResult<()> {
+    let mut fsm = Counter::new_with(
+        Context::default(),
+        FsmEventQueueVecShared::new(),
+        InspectNull::new(),
+        FsmTimersNull,
+    )?;
+    fsm.start()?;
+
+    assert!(!fsm.wait_for_events(Duration::from_millis(1)));
+
+    let producers: Vec<_> = (0..4)
+        .map(|_| {
+            let mut sender = fsm.sender();
+            thread::spawn(move || {
+                for n in 1..=10 {
+                    sender.enqueue(Add(n)).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    let mut finisher = fsm.sender();
+    let finish = thread::spawn(move || {
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        finisher.enqueue(Finish).unwrap();
+    });
+
+    let done = FsmCurrentState::State(CounterCurrentState::Done);
+    while fsm.get_current_states()[0] != done {
+        if fsm.wait_for_events(Duration::from_secs(5)) {
+            fsm.dispatch_queue()?;
+        }
+    }
+    finish.join().unwrap();
+
+    assert_eq!(4 * 55, fsm.total);
+
+    Ok(())
+}
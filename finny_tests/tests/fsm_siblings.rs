@@ -0,0 +1,93 @@
+extern crate finny;
+
+use finny::{bundled::derive_more, finny_fsm, FsmFactory, FsmResult};
+
+#[derive(Default)]
+pub struct Sampling {
+    samples: Vec<u32>,
+}
+#[derive(Default)]
+pub struct Reporting {
+    last: Option<u32>,
+}
+#[derive(Default)]
+pub struct Idle;
+#[derive(Default)]
+pub struct Reported {
+    samples: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sample(u32);
+#[derive(Debug, Clone)]
+pub struct Report;
+#[derive(Debug, Clone)]
+pub struct Reset;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, ()>) -> BuiltFsm {
+    fsm.initial_states::<(Sampling, Idle)>();
+
+    // region 1
+
+    fsm.state::<Sampling>()
+        .on_event::<Sample>()
+        .internal_transition()
+        .action(|ev, _ctx, state| {
+            state.samples.push(ev.0);
+        });
+
+    fsm.state::<Sampling>()
+        .on_event::<Reset>()
+        .internal_transition()
+        .action(|_ev, _ctx, state, siblings| {
+            state.samples.clear();
+            let reported: &mut Reported = siblings.as_mut();
+            reported.samples = 0;
+        });
+
+    fsm.state::<Sampling>()
+        .on_event::<Report>()
+        .transition_to::<Reporting>()
+        .action(|_ev, _ctx, from, to| {
+            to.last = from.samples.last().cloned();
+        });
+
+    fsm.state::<Reporting>();
+
+    // region 2
+
+    fsm.state::<Idle>()
+        .on_event::<Report>()
+        .transition_to::<Reported>()
+        .action(|_ev, _ctx, _from, to, siblings| {
+            let sampling: &Sampling = siblings.as_ref();
+            to.samples = sampling.samples.len();
+        });
+
+    fsm.state::<Reported>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_siblings() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(())?;
+
+    fsm.start()?;
+    fsm.dispatch(Sample(7))?;
+    fsm.dispatch(Reset)?;
+    let sampling: &Sampling = fsm.get_state();
+    assert!(sampling.samples.is_empty());
+
+    fsm.dispatch(Sample(1))?;
+    fsm.dispatch(Sample(2))?;
+    fsm.dispatch(Report)?;
+
+    let reporting: &Reporting = fsm.get_state();
+    assert_eq!(Some(2), reporting.last);
+    let reported: &Reported = fsm.get_state();
+    assert_eq!(2, reported.samples);
+
+    Ok(())
+}
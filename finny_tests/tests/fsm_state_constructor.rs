@@ -0,0 +1,161 @@
+extern crate finny;
+
+use finny::{bundled::derive_more, finny_fsm, FsmFactory, FsmResult};
+
+#[derive(Default)]
+pub struct Context {
+    connections: usize,
+    sessions: Vec<u64>,
+}
+
+#[derive(Default)]
+pub struct Disconnected;
+#[derive(Default)]
+pub struct Connected {
+    session: u64,
+    connection: usize,
+    messages: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Connect {
+    session: u64,
+}
+#[derive(Debug, Clone)]
+pub struct Message;
+#[derive(Debug, Clone)]
+pub struct Disconnect;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, Context>) -> BuiltFsm {
+    fsm.initial_state::<Disconnected>();
+
+    fsm.state::<Disconnected>()
+        .on_event::<Connect>()
+        .transition_to_with::<Connected>(|ev, ctx| Connected {
+            session: ev.session,
+            connection: ctx.connections + 1,
+            messages: 0,
+        })
+        .action(|_ev, ctx, _from, to| {
+            ctx.connections = to.connection;
+            ctx.sessions.push(to.session);
+        });
+
+    fsm.state::<Connected>()
+        .on_event::<Message>()
+        .internal_transition()
+        .action(|_ev, _ctx, state| {
+            state.messages += 1;
+        });
+
+    fsm.state::<Connected>()
+        .on_event::<Disconnect>()
+        .transition_to::<Disconnected>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_state_constructor() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(Context::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(Connect { session: 10 })?;
+    fsm.dispatch(Message)?;
+    fsm.dispatch(Message)?;
+    let state: &Connected = fsm.get_state();
+    assert_eq!(10, state.session);
+    assert_eq!(1, state.connection);
+    assert_eq!(2, state.messages);
+
+    fsm.dispatch(Disconnect)?;
+    fsm.dispatch(Connect { session: 20 })?;
+    let state: &Connected = fsm.get_state();
+    assert_eq!(20, state.session);
+    assert_eq!(2, state.connection);
+    assert_eq!(0, state.messages);
+
+    assert_eq!(vec![10, 20], fsm.sessions);
+
+    Ok(())
+}
+
+/// Only exists while the socket is open, there's no meaningful default.
+pub struct Socket {
+    port: u16,
+}
+
+#[derive(Default)]
+pub struct Closed;
+pub struct Listening {
+    socket: Socket,
+}
+
+#[derive(Debug, Clone)]
+pub struct Listen {
+    port: u16,
+}
+#[derive(Debug, Clone)]
+pub struct Close;
+
+// the default storage holds every state from the start, so the state needs a factory
+impl finny::FsmStateFactory<Server> for Listening {
+    fn new_state(_context: &Context) -> FsmResult<Self> {
+        Ok(Listening {
+            socket: Socket { port: 0 },
+        })
+    }
+}
+
+#[finny_fsm]
+fn build_server_fsm(mut fsm: FsmBuilder<Server, Context>) -> BuiltFsm {
+    fsm.initial_state::<Closed>();
+
+    fsm.state::<Closed>()
+        .on_event::<Listen>()
+        .transition_to_with::<Listening>(|ev, _ctx| Listening {
+            socket: Socket { port: ev.port },
+        });
+
+    fsm.state::<Listening>()
+        .on_event::<Close>()
+        .transition_to::<Closed>();
+
+    fsm.build()
+}
+
+// with the enum storage, the constructor is all the state needs
+#[finny_fsm]
+fn build_server_enum_fsm(mut fsm: FsmBuilder<ServerEnum, Context>) -> BuiltFsm {
+    fsm.states_storage_enum();
+    fsm.initial_state::<Closed>();
+
+    fsm.state::<Closed>()
+        .on_event::<Listen>()
+        .transition_to_with::<Socket>(|ev, _ctx| Socket { port: ev.port });
+
+    fsm.state::<Socket>()
+        .on_event::<Close>()
+        .transition_to::<Closed>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_state_constructor_no_default() -> FsmResult<()> {
+    let mut fsm = Server::new(Context::default())?;
+    fsm.start()?;
+    fsm.dispatch(Listen { port: 8080 })?;
+    let state: &Listening = fsm.get_state();
+    assert_eq!(8080, state.socket.port);
+
+    let mut fsm = ServerEnum::new(Context::default())?;
+    fsm.start()?;
+    fsm.dispatch(Listen { port: 8081 })?;
+    let state: &Socket = fsm.get_state();
+    assert_eq!(8081, state.port);
+    fsm.dispatch(Close)?;
+
+    Ok(())
+}
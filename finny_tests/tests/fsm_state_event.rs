@@ -0,0 +1,91 @@
+extern crate finny;
+
+use finny::{bundled::derive_more, finny_fsm, FsmCurrentState, FsmEvent, FsmFactory, FsmResult};
+
+#[derive(Default)]
+pub struct Context {
+    exited_with: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Disconnected {
+    entered_on_start: bool,
+}
+#[derive(Default)]
+pub struct Connected {
+    port: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct Connect {
+    port: u16,
+}
+#[derive(Debug, Clone)]
+pub struct Reconnect {
+    port: u16,
+}
+#[derive(Debug, Clone)]
+pub struct Disconnect;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, Context>) -> BuiltFsm {
+    fsm.initial_state::<Disconnected>();
+
+    fsm.state::<Disconnected>()
+        .on_entry(|state, _ctx, ev| {
+            state.entered_on_start = matches!(ev, FsmEvent::Start);
+        })
+        .on_event::<Connect>()
+        .transition_to::<Connected>();
+
+    fsm.state::<Connected>()
+        .on_entry(|state, _ctx, ev| match ev {
+            FsmEvent::Event(StateMachineEvents::Connect(Connect { port }))
+            | FsmEvent::Event(StateMachineEvents::Reconnect(Reconnect { port })) => {
+                state.port = *port;
+            }
+            _ => (),
+        })
+        .on_exit(|_state, ctx, ev| {
+            if let FsmEvent::Event(ev) = ev {
+                ctx.exited_with.push(ev.as_ref().to_string());
+            }
+        })
+        .on_event::<Disconnect>()
+        .transition_to::<Disconnected>();
+
+    fsm.state::<Connected>()
+        .on_event::<Reconnect>()
+        .self_transition();
+
+    fsm.build()
+}
+
+#[test]
+fn test_state_actions_event() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(Context::default())?;
+
+    fsm.start()?;
+    let state: &Disconnected = fsm.get_state();
+    assert!(state.entered_on_start);
+
+    fsm.dispatch(Connect { port: 80 })?;
+    let state: &Connected = fsm.get_state();
+    assert_eq!(80, state.port);
+
+    fsm.dispatch(Reconnect { port: 443 })?;
+    let state: &Connected = fsm.get_state();
+    assert_eq!(443, state.port);
+
+    fsm.dispatch(Disconnect)?;
+    assert_eq!(
+        FsmCurrentState::State(StateMachineCurrentState::Disconnected),
+        fsm.get_current_states()[0]
+    );
+    let state: &Disconnected = fsm.get_state();
+    assert!(!state.entered_on_start);
+
+    assert_eq!(vec!["Reconnect", "Disconnect"], fsm.exited_with);
+
+    Ok(())
+}
@@ -0,0 +1,187 @@
+extern crate finny;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use finny::{
+    bundled::derive_more, finny_fsm, FsmCurrentState, FsmFactory, FsmResult, FsmStatesTryAsRef,
+};
+
+static BUFFERS_DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default)]
+pub struct Context {
+    transferred: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct Idle;
+pub struct Receiving {
+    buffer: [u8; 256],
+    len: usize,
+}
+pub struct Sending {
+    buffer: [u8; 256],
+    len: usize,
+}
+
+impl Default for Receiving {
+    fn default() -> Self {
+        Self {
+            buffer: [0; 256],
+            len: 0,
+        }
+    }
+}
+
+impl Default for Sending {
+    fn default() -> Self {
+        Self {
+            buffer: [0; 256],
+            len: 0,
+        }
+    }
+}
+
+impl Drop for Receiving {
+    fn drop(&mut self) {
+        BUFFERS_DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Sending {
+    fn drop(&mut self) {
+        BUFFERS_DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Default)]
+pub struct LedOff;
+#[derive(Default)]
+pub struct LedOn {
+    toggles: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Receive;
+#[derive(Debug, Clone)]
+pub struct Byte(u8);
+#[derive(Debug, Clone)]
+pub struct Send;
+#[derive(Debug, Clone)]
+pub struct Done;
+#[derive(Debug, Clone)]
+pub struct Toggle;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, Context>) -> BuiltFsm {
+    fsm.states_storage_enum();
+    fsm.initial_states::<(Idle, LedOff)>();
+
+    // region 1
+
+    fsm.state::<Idle>()
+        .on_event::<Receive>()
+        .transition_to::<Receiving>();
+
+    fsm.state::<Receiving>()
+        .on_event::<Byte>()
+        .internal_transition()
+        .action(|ev, _ctx, state| {
+            state.buffer[state.len] = ev.0;
+            state.len += 1;
+        });
+
+    // only the active state can be borrowed
+    fsm.state::<Receiving>()
+        .on_event::<Send>()
+        .transition_to::<Sending>()
+        .guard(|_ev, _ctx, states| {
+            let receiving: Option<&Receiving> = states.try_as_ref();
+            let sending: Option<&Sending> = states.try_as_ref();
+            sending.is_none() && receiving.map(|r| r.len > 0).unwrap_or(false)
+        })
+        .action(|_ev, _ctx, from, to| {
+            to.buffer = from.buffer;
+            to.len = from.len;
+        });
+
+    fsm.state::<Sending>()
+        .on_event::<Done>()
+        .transition_to::<Idle>()
+        .action(|_ev, ctx, from, _to| {
+            ctx.transferred.extend_from_slice(&from.buffer[..from.len]);
+        });
+
+    // region 2
+
+    fsm.state::<LedOff>()
+        .on_event::<Toggle>()
+        .transition_to_with::<LedOn>(|_ev, _ctx| LedOn { toggles: 1 });
+
+    fsm.state::<LedOn>()
+        .on_event::<Toggle>()
+        .self_transition()
+        .action(|_ev, _ctx, state| {
+            state.toggles += 1;
+        });
+
+    fsm.build()
+}
+
+// only one of the buffers is stored at a time
+const _: () = assert!(StateMachineStates::REGION_SIZES[0] < 2 * 256);
+const _: () = assert!(StateMachineStates::SIZE < 2 * 256);
+
+#[test]
+fn test_states_enum() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(Context::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(Receive)?;
+    fsm.dispatch(Byte(1))?;
+    fsm.dispatch(Byte(2))?;
+    let state: &Receiving = fsm.get_state();
+    assert_eq!(2, state.len);
+    assert_eq!(0, BUFFERS_DROPPED.load(Ordering::SeqCst));
+
+    fsm.dispatch(Send)?;
+    assert_eq!(1, BUFFERS_DROPPED.load(Ordering::SeqCst));
+    let state: &Sending = fsm.get_state();
+    assert_eq!(&[1, 2], &state.buffer[..state.len]);
+
+    fsm.dispatch(Done)?;
+    assert_eq!(2, BUFFERS_DROPPED.load(Ordering::SeqCst));
+    assert_eq!(vec![1, 2], fsm.transferred);
+
+    fsm.dispatch(Toggle)?;
+    fsm.dispatch(Toggle)?;
+    let state: &LedOn = fsm.get_state();
+    assert_eq!(2, state.toggles);
+
+    assert_eq!(
+        [
+            FsmCurrentState::State(StateMachineCurrentState::Idle),
+            FsmCurrentState::State(StateMachineCurrentState::LedOn)
+        ],
+        fsm.get_current_states()
+    );
+
+    // stopping drops the active states
+    fsm.dispatch(Receive)?;
+    assert!(fsm.try_get_state::<Receiving>().is_some());
+    fsm.stop()?;
+    assert_eq!(3, BUFFERS_DROPPED.load(Ordering::SeqCst));
+    assert!(fsm.try_get_state::<Receiving>().is_none());
+    assert!(fsm.try_get_state::<LedOn>().is_none());
+
+    Ok(())
+}
+
+#[test]
+#[should_panic]
+fn test_states_enum_inactive() {
+    let mut fsm = StateMachine::new(Context::default()).unwrap();
+    fsm.start().unwrap();
+
+    let _state: &Receiving = fsm.get_state();
+}
@@ -0,0 +1,87 @@
+extern crate finny;
+
+use finny::{
+    bundled::derive_more, finny_fsm, FsmError, FsmEventQueue, FsmFactory, FsmResult, FsmStepLimit,
+    FsmStepLimitExceeded,
+};
+
+#[derive(Default)]
+pub struct Context {
+    pings: usize,
+}
+
+#[derive(Default)]
+pub struct Ping;
+#[derive(Default)]
+pub struct Pong;
+
+pub struct Hit;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Rally, Context>) -> BuiltFsm {
+    fsm.initial_state::<Ping>();
+
+    // the ball is always returned
+    fsm.state::<Ping>()
+        .on_event::<Hit>()
+        .transition_to::<Pong>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.pings += 1;
+            ctx.queue.enqueue(Hit).unwrap();
+        });
+
+    fsm.state::<Pong>()
+        .on_event::<Hit>()
+        .transition_to::<Ping>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.queue.enqueue(Hit).unwrap();
+        });
+
+    fsm.build()
+}
+
+#[test]
+fn test_step_limit() -> FsmResult<()> {
+    let mut fsm = Rally::new(Context::default())?;
+    fsm.step_limit = FsmStepLimit::new(10);
+    fsm.start()?;
+
+    match fsm.dispatch(Hit) {
+        Err(FsmError::StepLimitExceeded(FsmStepLimitExceeded {
+            steps,
+            last_events,
+            cycle,
+        })) => {
+            assert_eq!(10, steps);
+            assert_eq!(4, last_events.len());
+            assert!(last_events.iter().all(|ev| *ev == "Hit"));
+            assert!(!cycle);
+        }
+        _ => panic!("Expected the step limit to be exceeded."),
+    }
+    assert_eq!(5, fsm.pings);
+
+    // the returned ball is kept for the next dispatch
+    assert_eq!(1, fsm.internal_queue.len());
+    assert!(fsm.dispatch_queue().is_err());
+    assert_eq!(10, fsm.pings);
+
+    Ok(())
+}
+
+#[test]
+fn test_step_limit_cycle() -> FsmResult<()> {
+    let mut fsm = Rally::new(Context::default())?;
+    fsm.step_limit = FsmStepLimit::new(1000).with_cycle_detection();
+    fsm.start()?;
+
+    match fsm.dispatch(Hit) {
+        Err(FsmError::StepLimitExceeded(e)) => {
+            assert!(e.cycle);
+            assert!(e.steps < 1000);
+        }
+        _ => panic!("Expected a cycle."),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+extern crate finny;
+
+use std::time::Duration;
+
+use finny::{
+    bundled::derive_more,
+    driver::{run_tokio, run_tokio_with_errors},
+    finny_fsm,
+    inspect::null::InspectNull,
+    timers::tokio::TimersTokio,
+    FsmError, FsmEventQueueVec, FsmFactory, FsmResult, FsmTimers,
+};
+use tokio::{sync::mpsc, time::Instant};
+
+#[derive(Default)]
+pub struct Context {
+    blinks: usize,
+    blinked_at: Vec<Instant>,
+}
+
+#[derive(Default)]
+pub struct Off;
+#[derive(Default)]
+pub struct Blinking;
+
+pub struct Start;
+pub struct Blink;
+pub struct Halt;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Lamp, Context>) -> BuiltFsm {
+    fsm.initial_state::<Off>();
+
+    fsm.state::<Off>()
+        .on_event::<Start>()
+        .transition_to::<Blinking>();
+
+    fsm.state::<Blinking>()
+        .on_entry_start_timer(
+            |_ctx, timer| {
+                timer.timeout = Duration::from_millis(100);
+                timer.renew = true;
+            },
+            |_ctx, _state| Some(Blink.into()),
+        )
+        .with_timer_ty::<BlinkTimer>();
+
+    fsm.state::<Blinking>()
+        .on_event::<Blink>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.blinks += 1;
+            ctx.blinked_at.push(Instant::now());
+        });
+
+    fsm.state::<Blinking>()
+        .on_event::<Halt>()
+        .transition_to::<Off>();
+
+    fsm.build()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_tokio_driver() -> FsmResult<()> {
+    let mut fsm = Lamp::new_with(
+        Context::default(),
+        FsmEventQueueVec::new(),
+        InspectNull::new(),
+        TimersTokio::new(),
+    )?;
+    fsm.start()?;
+
+    let (events, mut received) = mpsc::channel(8);
+    let started_at = Instant::now();
+
+    let producer = tokio::spawn(async move {
+        events.send(Start.into()).await.ok();
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        events.send(Halt.into()).await.ok();
+    });
+
+    run_tokio(&mut fsm, &mut received).await;
+    producer.await.unwrap();
+
+    assert_eq!(3, fsm.blinks);
+    for (n, blinked_at) in fsm.blinked_at.iter().enumerate() {
+        assert_eq!(
+            Duration::from_millis(100 * (n as u64 + 1)),
+            blinked_at.duration_since(started_at)
+        );
+    }
+
+    // the timer was canceled on the exit of the state
+    assert_eq!(None, fsm.timers.get_triggered_timer());
+
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_tokio_driver_errors() -> FsmResult<()> {
+    let mut fsm = Lamp::new_with(
+        Context::default(),
+        FsmEventQueueVec::new(),
+        InspectNull::new(),
+        TimersTokio::new(),
+    )?;
+    fsm.start()?;
+
+    let (events, mut received) = mpsc::channel(8);
+    events.send(Halt.into()).await.ok();
+    drop(events);
+
+    // the lamp that is off can't be halted
+    let mut errors = vec![];
+    run_tokio_with_errors(&mut fsm, &mut received, |e| errors.push(e)).await;
+    assert_eq!(vec![FsmError::NoTransition], errors);
+
+    Ok(())
+}
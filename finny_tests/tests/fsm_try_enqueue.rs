@@ -0,0 +1,123 @@
+extern crate finny;
+
+use std::time::Duration;
+
+use finny::{
+    bundled::derive_more, finny_fsm, inspect::null::InspectNull, FsmError, FsmEventQueue,
+    FsmEventQueueArray, FsmEventQueueSender, FsmFactory, FsmOutputsNull, FsmQueuedEventError,
+    FsmResult, FsmTimers, TimerSettings,
+};
+
+#[derive(Default)]
+pub struct Context {
+    ticks: usize,
+}
+
+#[derive(Default)]
+pub struct Heating;
+
+pub struct Tick(usize);
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Heater, Context>) -> BuiltFsm {
+    fsm.initial_state::<Heating>();
+
+    fsm.state::<Heating>()
+        .on_event::<Tick>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.ticks += 1;
+        });
+
+    fsm.state::<Heating>()
+        .on_entry_start_timer(
+            |_ctx, timer| {
+                timer.timeout = Duration::from_millis(10);
+                timer.renew = true;
+            },
+            |_ctx, _state| Some(Tick(0).into()),
+        )
+        .with_timer_ty::<TickTimer>();
+
+    fsm.build()
+}
+
+/// Timers that are triggered by the test.
+#[derive(Default)]
+pub struct ManualTimers {
+    triggered: Vec<HeaterTimers>,
+}
+
+impl FsmTimers<Heater> for ManualTimers {
+    fn create(&mut self, _id: HeaterTimers, _settings: &TimerSettings) -> FsmResult<()> {
+        Ok(())
+    }
+
+    fn cancel(&mut self, _id: HeaterTimers) -> FsmResult<()> {
+        Ok(())
+    }
+
+    fn get_triggered_timer(&mut self) -> Option<HeaterTimers> {
+        self.triggered.pop()
+    }
+}
+
+#[test]
+fn test_try_enqueue() {
+    let mut queue = FsmEventQueueArray::<Heater, 1>::new();
+    assert!(queue.try_enqueue(Tick(1)).is_ok());
+
+    match queue.try_enqueue(Tick(2)) {
+        Err((FsmError::QueueOverCapacity, HeaterEvents::Tick(Tick(n)))) => assert_eq!(2, n),
+        _ => panic!("Expected the event to be rejected."),
+    }
+
+    // the rejected event can be retried once there is room for it
+    assert!(queue.dequeue().is_some());
+    assert!(queue.try_enqueue(Tick(2)).is_ok());
+    assert_eq!(1, queue.len());
+}
+
+#[test]
+fn test_timer_queue_over_capacity() -> FsmResult<()> {
+    let mut fsm = Heater::new_with_internal_queue(
+        Context::default(),
+        FsmEventQueueArray::<_, 1>::new(),
+        FsmEventQueueArray::<_, 1>::new(),
+        InspectNull::new(),
+        ManualTimers::default(),
+        FsmOutputsNull,
+    )?;
+    fsm.start()?;
+
+    fsm.internal_queue.enqueue(Tick(1))?;
+    fsm.timers.triggered.push(HeaterTimers::TickTimer);
+    fsm.timers.triggered.push(HeaterTimers::TickTimer);
+
+    // both of the timers are handled, their errors are kept in the outcome
+    let outcome = fsm.dispatch_timer_events()?;
+    assert_eq!(3, outcome.queued_events);
+    assert_eq!(
+        &[
+            FsmQueuedEventError {
+                index: 0,
+                error: FsmError::QueueOverCapacity
+            },
+            FsmQueuedEventError {
+                index: 1,
+                error: FsmError::QueueOverCapacity
+            }
+        ],
+        &outcome.queue_errors[..]
+    );
+    assert_eq!(2, outcome.rejected_events.len());
+    assert!(fsm.timers.triggered.is_empty());
+    assert_eq!(1, fsm.ticks);
+
+    fsm.timers.triggered.push(HeaterTimers::TickTimer);
+    let outcome = fsm.dispatch_timer_events()?;
+    assert!(outcome.queue_errors.is_empty());
+    assert_eq!(2, fsm.ticks);
+
+    Ok(())
+}